@@ -0,0 +1,417 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{set_aside, unix_now, unix_now_ms, write_atomic};
+use crate::text::{decode_entities, find_ignore_case};
+
+const NETSCAPE_HEADER: &str = "<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file.
+     It will be read and overwritten.
+     DO NOT EDIT! -->
+<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+";
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Bookmark,
+    Folder,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BookmarkNode {
+    id: String,
+    #[serde(rename = "type")]
+    kind: NodeKind,
+    title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<BookmarkNode>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    added: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct NewBookmark {
+    #[serde(rename = "type")]
+    kind: NodeKind,
+    title: String,
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BookmarkPatch {
+    title: Option<String>,
+    url: Option<String>,
+}
+
+type Tree = Vec<BookmarkNode>;
+
+// The bookmark tree, kept in one JSON file. Every change is written through
+// before the updated tree is handed back to the caller.
+pub struct BookmarkStore {
+    path: PathBuf,
+    tree: Mutex<Tree>,
+    seq: AtomicU64,
+    revision: AtomicU64,
+    replaced: Option<PathBuf>,
+}
+
+impl BookmarkStore {
+    // An unreadable file is moved aside before the empty tree can be written over it.
+    pub fn load(path: PathBuf) -> Result<Self, String> {
+        let (tree, replaced) = match fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice(&bytes) {
+                Ok(tree) => (tree, None),
+                Err(_) => (Tree::new(), Some(set_aside(&path)?)),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (Tree::new(), None),
+            Err(_) => (Tree::new(), Some(set_aside(&path)?)),
+        };
+        Ok(Self {
+            path,
+            tree: Mutex::new(tree),
+            seq: AtomicU64::new(unix_now_ms()),
+            revision: AtomicU64::new(0),
+            replaced,
+        })
+    }
+
+    pub fn replaced(&self) -> Option<&Path> {
+        self.replaced.as_deref()
+    }
+
+    pub fn list(&self) -> Tree {
+        self.tree.lock().unwrap().clone()
+    }
+
+    // Bumped by every change to the tree.
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::Relaxed)
+    }
+
+    // Every bookmarked URL with its title, folders flattened away.
+    pub fn urls(&self) -> Vec<(String, String)> {
+        fn walk(nodes: &[BookmarkNode], out: &mut Vec<(String, String)>) {
+            for node in nodes {
+                if let Some(url) = &node.url {
+                    out.push((url.clone(), node.title.clone()));
+                }
+                walk(node.children.as_deref().unwrap_or_default(), out);
+            }
+        }
+        let mut out = Vec::new();
+        walk(&self.tree.lock().unwrap(), &mut out);
+        out
+    }
+
+    // Fills an empty tree only, so it never replaces bookmarks made since.
+    pub fn migrate(&self, legacy: Tree) -> Result<Tree, String> {
+        self.change(|tree| {
+            if tree.is_empty() {
+                *tree = legacy;
+            }
+            Ok(())
+        })
+    }
+
+    pub fn add(&self, parent_id: Option<&str>, new: NewBookmark) -> Result<Tree, String> {
+        self.change(|tree| {
+            let node = BookmarkNode {
+                id: self.fresh_id(tree),
+                kind: new.kind,
+                title: new.title,
+                url: new.url.filter(|u| !u.is_empty()),
+                children: (new.kind == NodeKind::Folder).then(Vec::new),
+                added: Some(unix_now()),
+            };
+            folder_children(tree, parent_id)?.push(node);
+            Ok(())
+        })
+    }
+
+    pub fn update(&self, id: &str, patch: BookmarkPatch) -> Result<Tree, String> {
+        self.change(|tree| {
+            let node = find_mut(tree, id).ok_or_else(|| not_found(id))?;
+            if let Some(title) = patch.title {
+                node.title = title;
+            }
+            if let Some(url) = patch.url {
+                node.url = Some(url).filter(|u| !u.is_empty());
+            }
+            Ok(())
+        })
+    }
+
+    pub fn remove(&self, id: &str) -> Result<Tree, String> {
+        self.change(|tree| {
+            take(tree, id);
+            Ok(())
+        })
+    }
+
+    pub fn remove_url(&self, url: &str) -> Result<Tree, String> {
+        self.change(|tree| {
+            retain_deep(tree, &|node| {
+                node.kind == NodeKind::Folder || node.url.as_deref() != Some(url)
+            });
+            Ok(())
+        })
+    }
+
+    // Moves a node into `folder_id` (the root when `None`), at `index` or at the end.
+    pub fn move_to(
+        &self,
+        id: &str,
+        folder_id: Option<&str>,
+        index: Option<usize>,
+    ) -> Result<Tree, String> {
+        self.change(|tree| {
+            let node = find_mut(tree, id).ok_or_else(|| not_found(id))?;
+            if let Some(folder_id) = folder_id {
+                if find_mut(std::slice::from_mut(node), folder_id).is_some() {
+                    return Err("a folder cannot be moved into itself".into());
+                }
+            }
+            let node = take(tree, id).ok_or_else(|| not_found(id))?;
+            let children = folder_children(tree, folder_id)?;
+            let index = index.unwrap_or(children.len()).min(children.len());
+            children.insert(index, node);
+            Ok(())
+        })
+    }
+
+    pub fn reorder(&self, parent_id: Option<&str>, from: usize, to: usize) -> Result<Tree, String> {
+        self.change(|tree| {
+            let children = folder_children(tree, parent_id)?;
+            if from >= children.len() {
+                return Err(format!("no bookmark at position {from}"));
+            }
+            let node = children.remove(from);
+            children.insert(to.min(children.len()), node);
+            Ok(())
+        })
+    }
+
+    // Appends everything found in a Netscape bookmarks file to the root.
+    pub fn import_html(&self, html: &str) -> Result<Tree, String> {
+        let mut imported = parse_html(html);
+        self.change(|tree| {
+            self.assign_ids(tree, &mut imported);
+            tree.append(&mut imported);
+            Ok(())
+        })
+    }
+
+    pub fn export_html(&self) -> String {
+        let mut out = String::from(NETSCAPE_HEADER);
+        write_html(&self.tree.lock().unwrap(), 1, &mut out);
+        out
+    }
+
+    fn change(&self, f: impl FnOnce(&mut Tree) -> Result<(), String>) -> Result<Tree, String> {
+        let mut tree = self.tree.lock().unwrap();
+        let mut next = tree.clone();
+        f(&mut next)?;
+        let json = serde_json::to_vec_pretty(&next).map_err(|e| e.to_string())?;
+        write_atomic(&self.path, &json)?;
+        *tree = next;
+        self.revision.fetch_add(1, Ordering::Relaxed);
+        Ok(tree.clone())
+    }
+
+    fn fresh_id(&self, tree: &mut Tree) -> String {
+        loop {
+            let id = format!("bk_{}", self.seq.fetch_add(1, Ordering::Relaxed));
+            if find_mut(tree, &id).is_none() {
+                return id;
+            }
+        }
+    }
+
+    fn assign_ids(&self, tree: &mut Tree, nodes: &mut [BookmarkNode]) {
+        for node in nodes {
+            node.id = self.fresh_id(tree);
+            if let Some(children) = &mut node.children {
+                self.assign_ids(tree, children);
+            }
+        }
+    }
+}
+
+fn not_found(id: &str) -> String {
+    format!("no bookmark with id `{id}`")
+}
+
+fn find_mut<'a>(nodes: &'a mut [BookmarkNode], id: &str) -> Option<&'a mut BookmarkNode> {
+    for node in nodes {
+        if node.id == id {
+            return Some(node);
+        }
+        if let Some(found) = node.children.as_mut().and_then(|c| find_mut(c, id)) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+fn folder_children<'a>(
+    tree: &'a mut Tree,
+    folder_id: Option<&str>,
+) -> Result<&'a mut Tree, String> {
+    let Some(folder_id) = folder_id else {
+        return Ok(tree);
+    };
+    let folder = find_mut(tree, folder_id)
+        .filter(|node| node.kind == NodeKind::Folder)
+        .ok_or_else(|| format!("no folder with id `{folder_id}`"))?;
+    Ok(folder.children.get_or_insert_with(Vec::new))
+}
+
+fn take(nodes: &mut Tree, id: &str) -> Option<BookmarkNode> {
+    if let Some(i) = nodes.iter().position(|node| node.id == id) {
+        return Some(nodes.remove(i));
+    }
+    nodes.iter_mut().filter_map(|node| node.children.as_mut()).find_map(|c| take(c, id))
+}
+
+fn retain_deep(nodes: &mut Tree, keep: &dyn Fn(&BookmarkNode) -> bool) {
+    nodes.retain(|node| keep(node));
+    for children in nodes.iter_mut().filter_map(|node| node.children.as_mut()) {
+        retain_deep(children, keep);
+    }
+}
+
+fn write_html(nodes: &[BookmarkNode], depth: usize, out: &mut String) {
+    let indent = "    ".repeat(depth);
+    out.push_str(&format!("{}<DL><p>\n", "    ".repeat(depth - 1)));
+    for node in nodes {
+        let added = node.added.map(|t| format!(" ADD_DATE=\"{t}\"")).unwrap_or_default();
+        match node.kind {
+            NodeKind::Folder => {
+                out.push_str(&format!("{indent}<DT><H3{added}>{}</H3>\n", escape(&node.title)));
+                write_html(node.children.as_deref().unwrap_or_default(), depth + 1, out);
+            }
+            NodeKind::Bookmark => out.push_str(&format!(
+                "{indent}<DT><A HREF=\"{}\"{added}>{}</A>\n",
+                escape(node.url.as_deref().unwrap_or_default()),
+                escape(&node.title)
+            )),
+        }
+    }
+    out.push_str(&format!("{}</DL><p>\n", "    ".repeat(depth - 1)));
+}
+
+struct Frame {
+    folder: Option<(String, Option<u64>)>,
+    nodes: Tree,
+}
+
+impl Frame {
+    fn close_into(self, parent: &mut Frame) {
+        match self.folder {
+            Some((title, added)) => parent.nodes.push(BookmarkNode {
+                id: String::new(),
+                kind: NodeKind::Folder,
+                title,
+                url: None,
+                children: Some(self.nodes),
+                added,
+            }),
+            None => parent.nodes.extend(self.nodes),
+        }
+    }
+}
+
+// Reads the Netscape format every browser exports: folders are `<H3>`
+// headings followed by a `<DL>` list, bookmarks are `<A HREF>` links.
+// Nodes come back without ids.
+fn parse_html(html: &str) -> Tree {
+    let mut stack = vec![Frame { folder: None, nodes: Vec::new() }];
+    let mut heading = None;
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let end = rest.find('>').unwrap_or(rest.len());
+        let tag = &rest[..end];
+        rest = &rest[(end + 1).min(rest.len())..];
+        let name = tag
+            .split(|c: char| c.is_ascii_whitespace())
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let added = || attr(tag, "add_date").and_then(|d| d.parse().ok());
+        match name.as_str() {
+            "h3" => {
+                let (title, after) = text_until(rest, "</h3");
+                rest = after;
+                heading = Some((title, added()));
+            }
+            "a" => {
+                let (title, after) = text_until(rest, "</a");
+                rest = after;
+                let Some(url) = attr(tag, "href") else { continue };
+                // Firefox smart folders and bookmarklets mean nothing here.
+                if url.starts_with("place:") || url.starts_with("javascript:") {
+                    continue;
+                }
+                let frame = stack.last_mut().unwrap();
+                frame.nodes.push(BookmarkNode {
+                    id: String::new(),
+                    kind: NodeKind::Bookmark,
+                    title: if title.is_empty() { url.clone() } else { title },
+                    url: Some(url),
+                    children: None,
+                    added: added(),
+                });
+            }
+            "dl" => stack.push(Frame { folder: heading.take(), nodes: Vec::new() }),
+            "/dl" if stack.len() > 1 => {
+                let frame = stack.pop().unwrap();
+                frame.close_into(stack.last_mut().unwrap());
+            }
+            _ => {}
+        }
+    }
+    while stack.len() > 1 {
+        let frame = stack.pop().unwrap();
+        frame.close_into(stack.last_mut().unwrap());
+    }
+    stack.pop().map(|frame| frame.nodes).unwrap_or_default()
+}
+
+fn text_until<'a>(html: &'a str, closing: &str) -> (String, &'a str) {
+    let end = find_ignore_case(html, closing).unwrap_or(html.len());
+    (decode_entities(html[..end].trim()), &html[end..])
+}
+
+fn attr(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find(name) {
+        let at = from + i;
+        from = at + name.len();
+        if !lower[..at].ends_with(|c: char| c.is_ascii_whitespace()) {
+            continue;
+        }
+        let Some(value) = tag[from..].trim_start().strip_prefix('=') else { continue };
+        let value = value.trim_start();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+            _ => value.split(|c: char| c.is_ascii_whitespace()).next().unwrap_or_default(),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
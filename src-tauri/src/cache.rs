@@ -0,0 +1,537 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::content;
+use crate::fetch::ConnectionInfo;
+use crate::storage::{unix_now, write_atomic};
+
+pub const MAX_BYTES: u64 = 64 * 1024 * 1024;
+const SAVED_MAX_BYTES: u64 = 4 * MAX_BYTES;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    file: String,
+    status: String,
+    status_details: String,
+    headers: Vec<(String, String)>,
+    connection: ConnectionInfo,
+    size: u64,
+    stored_at: u64,
+    last_used: u64,
+    fresh_until: Option<u64>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+pub enum Lookup {
+    Fresh(nwep::Response, ConnectionInfo),
+    Stale(Vec<nwep::Header>),
+    Miss,
+}
+
+#[derive(Serialize)]
+pub struct CacheEntryInfo {
+    url: String,
+    status: String,
+    size: u64,
+    stored_at: u64,
+    last_used: u64,
+    fresh: bool,
+}
+
+#[derive(Serialize)]
+pub struct CacheInfo {
+    entries: Vec<CacheEntryInfo>,
+    total_bytes: u64,
+    max_bytes: u64,
+    // The copies kept for offline use, counted apart from the cache itself.
+    saved_bytes: u64,
+}
+
+#[derive(Default)]
+struct Policy {
+    no_store: bool,
+    no_cache: bool,
+    max_age: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct OfflineMode(AtomicBool);
+
+impl OfflineMode {
+    pub fn get(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, enabled: bool) {
+        self.0.store(enabled, Ordering::Relaxed);
+    }
+}
+
+// Bodies in one directory next to a JSON index, kept under `max_bytes` by
+// dropping the least recently used.
+struct Store {
+    dir: PathBuf,
+    max_bytes: u64,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl Store {
+    fn load(dir: PathBuf, max_bytes: u64) -> Self {
+        let entries: HashMap<String, Entry> = fs::read(dir.join("index.json"))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self { dir, max_bytes, entries: Mutex::new(entries) }
+    }
+
+    fn get(&self, key: &str) -> Option<Entry> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    fn insert(&self, key: &str, mut entry: Entry, body: &[u8]) {
+        entry.file = body_file(key);
+        if write_atomic(&self.dir.join(&entry.file), body).is_err() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(key.to_string(), entry);
+        self.evict(&mut entries);
+        let _ = self.save(&entries);
+    }
+
+    fn remove(&self, key: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.remove(key) {
+            let _ = fs::remove_file(self.dir.join(&entry.file));
+            let _ = self.save(&entries);
+        }
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        self.remove_where(|_, _| true)
+    }
+
+    fn remove_where(&self, mut matches: impl FnMut(&str, &Entry) -> bool) -> Result<(), String> {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|key, entry| {
+            let remove = matches(key, entry);
+            if remove {
+                let _ = fs::remove_file(self.dir.join(&entry.file));
+            }
+            !remove
+        });
+        if entries.len() == before {
+            return Ok(());
+        }
+        self.save(&entries)
+    }
+
+    fn total_bytes(&self) -> u64 {
+        self.entries.lock().unwrap().values().map(|e| e.size).sum()
+    }
+
+    fn evict(&self, entries: &mut HashMap<String, Entry>) {
+        let mut total: u64 = entries.values().map(|e| e.size).sum();
+        while total > self.max_bytes {
+            let Some(key) = entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = entries.remove(&key) {
+                total -= entry.size;
+                let _ = fs::remove_file(self.dir.join(&entry.file));
+            }
+        }
+    }
+
+    fn read(&self, entry: &Entry) -> Option<nwep::Response> {
+        let body = fs::read(self.dir.join(&entry.file)).ok()?;
+        Some(nwep::Response {
+            status: entry.status.clone(),
+            status_details: entry.status_details.clone(),
+            headers: entry
+                .headers
+                .iter()
+                .map(|(name, value)| nwep::Header { name: name.clone(), value: value.clone() })
+                .collect(),
+            body,
+        })
+    }
+
+    fn save(&self, entries: &HashMap<String, Entry>) -> Result<(), String> {
+        let json = serde_json::to_vec(entries).map_err(|e| e.to_string())?;
+        write_atomic(&self.dir.join("index.json"), &json)
+    }
+}
+
+// Responses are keyed by their URL without the fragment, which pins both the
+// node id and the request path. The last successful response for each key is
+// also saved apart from the cache, so that clearing the cache, evicting from
+// it or an error answer never takes away the copy shown while offline.
+#[derive(Clone)]
+pub struct ResponseCache {
+    responses: Arc<Store>,
+    saved: Arc<Store>,
+}
+
+impl ResponseCache {
+    pub fn load(dir: PathBuf, saved_dir: PathBuf) -> Self {
+        Self {
+            responses: Arc::new(Store::load(dir, MAX_BYTES)),
+            saved: Arc::new(Store::load(saved_dir, SAVED_MAX_BYTES)),
+        }
+    }
+
+    pub fn lookup(&self, key: &str) -> Lookup {
+        let mut entries = self.responses.entries.lock().unwrap();
+        let Some(entry) = entries.get_mut(key) else {
+            return Lookup::Miss;
+        };
+        let now = unix_now();
+        entry.last_used = now;
+        if entry.fresh_until.is_some_and(|until| now < until) {
+            let entry = entry.clone();
+            drop(entries);
+            return match self.responses.read(&entry) {
+                Some(resp) => Lookup::Fresh(resp, entry.connection),
+                None => {
+                    self.responses.remove(key);
+                    Lookup::Miss
+                }
+            };
+        }
+
+        if entry.etag.is_none() && entry.last_modified.is_none() {
+            return Lookup::Miss;
+        }
+        let mut validators = Vec::new();
+        if let Some(etag) = &entry.etag {
+            validators.push(nwep::Header { name: "if-none-match".into(), value: etag.clone() });
+        }
+        if let Some(modified) = &entry.last_modified {
+            validators.push(nwep::Header {
+                name: "if-modified-since".into(),
+                value: modified.clone(),
+            });
+        }
+        Lookup::Stale(validators)
+    }
+
+    // The last successful response for `key`, however old, and its age in seconds.
+    pub fn last_good(&self, key: &str) -> Option<(nwep::Response, ConnectionInfo, u64)> {
+        let entry = self.saved.get(key)?;
+        let age = unix_now().saturating_sub(entry.stored_at);
+        Some((self.saved.read(&entry)?, entry.connection, age))
+    }
+
+    // Called with the answer to a conditional request. Returns the cached
+    // response when the server confirmed it is still current.
+    pub fn revalidate(
+        &self,
+        key: &str,
+        resp: &nwep::Response,
+    ) -> Option<(nwep::Response, ConnectionInfo)> {
+        if !is_not_modified(&resp.status) {
+            return None;
+        }
+        let mut entries = self.responses.entries.lock().unwrap();
+        let entry = entries.get_mut(key)?;
+        let now = unix_now();
+        entry.fresh_until = freshness(&policy(&resp.headers), now);
+        entry.last_used = now;
+        let entry = entry.clone();
+        let _ = self.responses.save(&entries);
+        drop(entries);
+        Some((self.responses.read(&entry)?, entry.connection))
+    }
+
+    pub fn store(&self, key: &str, resp: &nwep::Response, connection: &ConnectionInfo) {
+        let policy = policy(&resp.headers);
+        let cacheable = is_success(&resp.status)
+            && !policy.no_store
+            && (resp.body.len() as u64) < MAX_BYTES / 4;
+        if !cacheable {
+            self.responses.remove(key);
+            return;
+        }
+
+        let now = unix_now();
+        let entry = Entry {
+            file: String::new(),
+            status: resp.status.clone(),
+            status_details: resp.status_details.clone(),
+            headers: resp.headers.iter().map(|h| (h.name.clone(), h.value.clone())).collect(),
+            connection: connection.clone(),
+            size: resp.body.len() as u64,
+            stored_at: now,
+            last_used: now,
+            fresh_until: freshness(&policy, now),
+            etag: content::header(&resp.headers, "etag").map(str::to_string),
+            last_modified: content::header(&resp.headers, "last-modified").map(str::to_string),
+        };
+        self.saved.insert(key, entry.clone(), &resp.body);
+        // Without freshness or validators `lookup` could never reuse it.
+        if entry.fresh_until.is_some() || entry.etag.is_some() || entry.last_modified.is_some() {
+            self.responses.insert(key, entry, &resp.body);
+        } else {
+            self.responses.remove(key);
+        }
+    }
+
+    pub fn info(&self) -> CacheInfo {
+        let entries = self.responses.entries.lock().unwrap();
+        let now = unix_now();
+        let mut list: Vec<CacheEntryInfo> = entries
+            .iter()
+            .map(|(url, e)| CacheEntryInfo {
+                url: url.clone(),
+                status: e.status.clone(),
+                size: e.size,
+                stored_at: e.stored_at,
+                last_used: e.last_used,
+                fresh: e.fresh_until.is_some_and(|until| now < until),
+            })
+            .collect();
+        list.sort_by_key(|e| std::cmp::Reverse(e.last_used));
+        CacheInfo {
+            total_bytes: entries.values().map(|e| e.size).sum(),
+            entries: list,
+            max_bytes: MAX_BYTES,
+            saved_bytes: self.saved.total_bytes(),
+        }
+    }
+
+    pub fn clear(&self) -> Result<(), String> {
+        self.responses.clear()?;
+        self.saved.clear()
+    }
+
+    // Saved copies go along with the history of visiting them.
+    pub fn forget_saved(&self, key: &str) -> Result<(), String> {
+        self.saved.remove_where(|saved, _| saved == key)
+    }
+
+    // Every copy saved between `from` and `to`, in unix ms, either bound open.
+    pub fn forget_saved_between(&self, from: Option<i64>, to: Option<i64>) -> Result<(), String> {
+        self.saved.remove_where(|_, entry| {
+            let at = entry.stored_at as i64 * 1000;
+            from.is_none_or(|from| at >= from) && to.is_none_or(|to| at < to)
+        })
+    }
+}
+
+fn body_file(key: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    format!("{:016x}.body", hasher.finish())
+}
+
+fn policy(headers: &[nwep::Header]) -> Policy {
+    let mut policy = Policy::default();
+    let Some(value) = content::header(headers, "cache-control") else {
+        return policy;
+    };
+    for directive in value.split(',') {
+        let (name, arg) = match directive.split_once('=') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim().trim_matches('"'))),
+            None => (directive.trim(), None),
+        };
+        match name.to_ascii_lowercase().as_str() {
+            "no-store" => policy.no_store = true,
+            "no-cache" => policy.no_cache = true,
+            "max-age" => policy.max_age = arg.and_then(|a| a.parse().ok()),
+            _ => {}
+        }
+    }
+    policy
+}
+
+fn freshness(policy: &Policy, now: u64) -> Option<u64> {
+    if policy.no_cache {
+        return None;
+    }
+    policy.max_age.map(|age| now + age)
+}
+
+pub fn is_success(status: &str) -> bool {
+    matches!(status.to_ascii_lowercase().as_str(), "200" | "ok" | "success")
+}
+
+fn is_not_modified(status: &str) -> bool {
+    matches!(status.to_ascii_lowercase().as_str(), "304" | "not_modified")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A cache in its own directory, removed again when the test ends.
+    struct TestCache(ResponseCache, PathBuf);
+
+    impl TestCache {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("eclipse-cache-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            Self(ResponseCache::load(dir.join("responses"), dir.join("saved")), dir)
+        }
+    }
+
+    impl Drop for TestCache {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.1);
+        }
+    }
+
+    const KEY: &str = "web://[node1]:7000/page";
+
+    fn response(status: &str, headers: &[(&str, &str)], body: &str) -> nwep::Response {
+        nwep::Response {
+            status: status.into(),
+            status_details: String::new(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| nwep::Header { name: (*name).into(), value: (*value).into() })
+                .collect(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn connection() -> ConnectionInfo {
+        serde_json::from_value(serde_json::json!({
+            "client_node_id": "client",
+            "server_node_id": "node1",
+            "server_pubkey": "00",
+        }))
+        .unwrap()
+    }
+
+    fn validators(lookup: Lookup) -> Vec<(String, String)> {
+        match lookup {
+            Lookup::Stale(headers) => headers.into_iter().map(|h| (h.name, h.value)).collect(),
+            Lookup::Fresh(..) => panic!("expected a stale entry, found a fresh one"),
+            Lookup::Miss => panic!("expected a stale entry, found none"),
+        }
+    }
+
+    #[test]
+    fn reads_cache_control_directives() {
+        let headers = response("200", &[("Cache-Control", "public, max-age=\"60\", No-Cache")], "")
+            .headers;
+        let policy = policy(&headers);
+        assert_eq!(policy.max_age, Some(60));
+        assert!(policy.no_cache && !policy.no_store);
+        assert_eq!(freshness(&policy, 1000), None);
+        assert_eq!(freshness(&Policy { max_age: Some(60), ..Policy::default() }, 1000), Some(1060));
+        assert_eq!(freshness(&Policy::default(), 1000), None);
+    }
+
+    #[test]
+    fn serves_within_max_age() {
+        let cache = TestCache::new("max-age");
+        let resp = response("200", &[("cache-control", "max-age=60")], "hi");
+        cache.0.store(KEY, &resp, &connection());
+        match cache.0.lookup(KEY) {
+            Lookup::Fresh(resp, _) => assert_eq!(resp.body, b"hi"),
+            _ => panic!("expected a fresh entry"),
+        }
+        assert!(matches!(cache.0.lookup("web://[node1]:7000/other"), Lookup::Miss));
+    }
+
+    #[test]
+    fn revalidates_with_the_stored_validators() {
+        let cache = TestCache::new("validators");
+        let headers = [("etag", "\"v1\""), ("cache-control", "no-cache, max-age=60")];
+        cache.0.store(KEY, &response("200", &headers, "hi"), &connection());
+        assert_eq!(
+            validators(cache.0.lookup(KEY)),
+            [("if-none-match".to_string(), "\"v1\"".to_string())],
+        );
+
+        let modified = "Tue, 01 Sep 2026 00:00:00 GMT";
+        cache.0.store(KEY, &response("ok", &[("last-modified", modified)], "hi"), &connection());
+        assert_eq!(
+            validators(cache.0.lookup(KEY)),
+            [("if-modified-since".to_string(), modified.to_string())],
+        );
+    }
+
+    #[test]
+    fn misses_without_freshness_or_validators() {
+        let cache = TestCache::new("no-validators");
+        cache.0.store(KEY, &response("200", &[], "hi"), &connection());
+        assert!(matches!(cache.0.lookup(KEY), Lookup::Miss));
+    }
+
+    #[test]
+    fn not_modified_refreshes_the_stored_response() {
+        let cache = TestCache::new("not-modified");
+        cache.0.store(KEY, &response("200", &[("etag", "\"v1\"")], "hi"), &connection());
+        assert!(cache.0.revalidate(KEY, &response("200", &[], "new")).is_none());
+
+        let confirmed = response("not_modified", &[("cache-control", "max-age=60")], "");
+        let (resp, _) = cache.0.revalidate(KEY, &confirmed).unwrap();
+        assert_eq!(resp.body, b"hi");
+        assert!(matches!(cache.0.lookup(KEY), Lookup::Fresh(..)));
+    }
+
+    #[test]
+    fn does_not_keep_uncacheable_responses() {
+        let cache = TestCache::new("uncacheable");
+        let no_store = [("cache-control", "no-store, max-age=60")];
+        cache.0.store(KEY, &response("200", &no_store, "hi"), &connection());
+        assert!(matches!(cache.0.lookup(KEY), Lookup::Miss));
+        cache.0.store(KEY, &response("404", &[("cache-control", "max-age=60")], ""), &connection());
+        assert!(matches!(cache.0.lookup(KEY), Lookup::Miss));
+    }
+
+    #[test]
+    fn keeps_the_saved_copy_when_the_cache_drops_it() {
+        let cache = TestCache::new("saved");
+        cache.0.store(KEY, &response("200", &[("etag", "\"v1\"")], "hi"), &connection());
+        cache.0.store(KEY, &response("500", &[], "oops"), &connection());
+        let no_store = response("200", &[("cache-control", "no-store")], "new");
+        cache.0.store(KEY, &no_store, &connection());
+        assert!(matches!(cache.0.lookup(KEY), Lookup::Miss));
+        let (resp, _, _) = cache.0.last_good(KEY).unwrap();
+        assert_eq!(resp.body, b"hi");
+        assert!(cache.0.last_good("web://[node1]:7000/other").is_none());
+    }
+
+    #[test]
+    fn clears_and_forgets_saved_copies() {
+        let cache = TestCache::new("forget");
+        let other = "web://[node1]:7000/other";
+        for key in [KEY, other] {
+            cache.0.store(key, &response("200", &[], "hi"), &connection());
+        }
+        assert_eq!(cache.0.info().saved_bytes, 4);
+
+        cache.0.forget_saved(KEY).unwrap();
+        assert!(cache.0.last_good(KEY).is_none());
+        let stored_ms = unix_now() as i64 * 1000;
+        cache.0.forget_saved_between(Some(stored_ms + 1000), None).unwrap();
+        assert!(cache.0.last_good(other).is_some());
+        cache.0.forget_saved_between(None, Some(stored_ms + 1000)).unwrap();
+        assert!(cache.0.last_good(other).is_none());
+
+        let fresh = response("200", &[("cache-control", "max-age=60")], "hi");
+        cache.0.store(KEY, &fresh, &connection());
+        cache.0.clear().unwrap();
+        assert!(matches!(cache.0.lookup(KEY), Lookup::Miss));
+        assert!(cache.0.last_good(KEY).is_none());
+        assert_eq!(cache.0.info().saved_bytes, 0);
+    }
+}
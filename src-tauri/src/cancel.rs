@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct RequestRegistry {
+    tokens: Arc<Mutex<HashMap<String, CancelToken>>>,
+}
+
+impl RequestRegistry {
+    pub fn register(&self, id: Option<String>) -> Registration {
+        let token = CancelToken::default();
+        if let Some(id) = &id {
+            self.tokens.lock().unwrap().insert(id.clone(), token.clone());
+        }
+        Registration { registry: self.clone(), id, token }
+    }
+
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.tokens.lock().unwrap().get(id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+pub struct Registration {
+    registry: RequestRegistry,
+    id: Option<String>,
+    pub token: CancelToken,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Some(id) = &self.id {
+            self.registry.tokens.lock().unwrap().remove(id);
+        }
+    }
+}
+
+// Subresource loads belong to whatever document the content frame shows. The
+// frontend starts a new generation before it replaces that document, which
+// cancels every load the old one still had running.
+#[derive(Default)]
+pub struct FrameLoads(Mutex<CancelToken>);
+
+impl FrameLoads {
+    pub fn token(&self) -> CancelToken {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn restart(&self) {
+        std::mem::take(&mut *self.0.lock().unwrap()).cancel();
+    }
+}
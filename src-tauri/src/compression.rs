@@ -0,0 +1,122 @@
+use std::io::Read;
+
+pub const ACCEPT_ENCODING: &str = "zstd, br, gzip";
+
+// Guards against bodies that expand far beyond anything a page needs.
+const MAX_DECODED_BYTES: u64 = 256 * 1024 * 1024;
+
+pub fn accept_encoding() -> nwep::Header {
+    nwep::Header { name: "accept-encoding".into(), value: ACCEPT_ENCODING.into() }
+}
+
+// Undoes every coding listed in `content-encoding`, last applied first.
+pub fn decode(content_encoding: &str, body: Vec<u8>) -> Result<Vec<u8>, String> {
+    // Answers without a body, like a 304, may still name the coding.
+    if body.is_empty() {
+        return Ok(body);
+    }
+    let codings: Vec<String> = content_encoding
+        .split(',')
+        .map(|c| c.trim().to_ascii_lowercase())
+        .filter(|c| !c.is_empty() && c != "identity")
+        .collect();
+    codings.iter().rev().try_fold(body, |body, coding| decode_one(coding, &body))
+}
+
+fn decode_one(coding: &str, body: &[u8]) -> Result<Vec<u8>, String> {
+    let reader: Box<dyn Read + '_> = match coding {
+        "gzip" | "x-gzip" => Box::new(flate2::read::MultiGzDecoder::new(body)),
+        "deflate" => Box::new(flate2::read::ZlibDecoder::new(body)),
+        "br" => Box::new(brotli::Decompressor::new(body, 4096)),
+        "zstd" => Box::new(
+            zstd::stream::read::Decoder::new(body).map_err(|e| format!("zstd: {e}"))?,
+        ),
+        other => return Err(format!("unsupported content-encoding `{other}`")),
+    };
+    let mut out = Vec::new();
+    reader
+        .take(MAX_DECODED_BYTES + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| format!("{coding}: {e}"))?;
+    if out.len() as u64 > MAX_DECODED_BYTES {
+        return Err(format!("{coding}: decoded body exceeds {MAX_DECODED_BYTES} bytes"));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    const PAGE: &[u8] = b"<html><body>hello, hello, hello, compressed world</body></html>";
+
+    fn encode(coding: &str, body: &[u8]) -> Vec<u8> {
+        match coding {
+            "gzip" => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body).unwrap();
+                encoder.finish().unwrap()
+            }
+            "deflate" => {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body).unwrap();
+                encoder.finish().unwrap()
+            }
+            "br" => {
+                let mut out = Vec::new();
+                let mut encoder = brotli::CompressorWriter::new(&mut out, 4096, 5, 22);
+                encoder.write_all(body).unwrap();
+                drop(encoder);
+                out
+            }
+            "zstd" => zstd::encode_all(body, 0).unwrap(),
+            other => panic!("no encoder for {other}"),
+        }
+    }
+
+    #[test]
+    fn decodes_each_supported_coding() {
+        for coding in ["gzip", "deflate", "br", "zstd"] {
+            let decoded = decode(coding, encode(coding, PAGE));
+            assert_eq!(decoded.as_deref(), Ok(PAGE), "{coding}");
+        }
+        assert_eq!(decode("X-Gzip", encode("gzip", PAGE)).as_deref(), Ok(PAGE));
+    }
+
+    #[test]
+    fn undoes_stacked_codings_last_first() {
+        let body = encode("br", &encode("gzip", PAGE));
+        assert_eq!(decode("gzip, br", body).as_deref(), Ok(PAGE));
+    }
+
+    #[test]
+    fn passes_identity_through() {
+        assert_eq!(decode("", PAGE.to_vec()).as_deref(), Ok(PAGE));
+        assert_eq!(decode(" identity ", PAGE.to_vec()).as_deref(), Ok(PAGE));
+    }
+
+    #[test]
+    fn leaves_empty_bodies_alone() {
+        for coding in ["gzip", "deflate", "br", "zstd", "gzip, br", "compress"] {
+            assert_eq!(decode(coding, Vec::new()), Ok(Vec::new()), "{coding}");
+        }
+    }
+
+    #[test]
+    fn rejects_corrupt_and_unknown_streams() {
+        for coding in ["gzip", "deflate", "br", "zstd"] {
+            let mut body = encode(coding, PAGE);
+            body.truncate(body.len() / 2);
+            assert!(decode(coding, body).is_err(), "truncated {coding}");
+        }
+        let garbage = b"definitely not compressed".to_vec();
+        for coding in ["gzip", "deflate", "zstd"] {
+            assert!(decode(coding, garbage.clone()).is_err(), "garbage {coding}");
+        }
+        assert!(decode("compress", PAGE.to_vec()).unwrap_err().contains("compress"));
+    }
+}
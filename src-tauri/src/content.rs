@@ -0,0 +1,198 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+pub fn header<'a>(headers: &'a [nwep::Header], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
+}
+
+// Without a header the type is guessed, but never a charset: that is left to
+// `decode_text`, which can still find one in the page.
+pub fn detect_content_type(headers: &[nwep::Header], url: &str, body: &[u8]) -> String {
+    if let Some(value) = header(headers, "content-type") {
+        return value.to_string();
+    }
+    guess_from_extension(url)
+        .or_else(|| sniff(body))
+        .unwrap_or(if std::str::from_utf8(body).is_ok() || meta_charset(body).is_some() {
+            "text/html"
+        } else {
+            "application/octet-stream"
+        })
+        .to_string()
+}
+
+fn guess_from_extension(url: &str) -> Option<&'static str> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let file = path.rsplit('/').next().unwrap_or(path);
+    let ext = file.rsplit_once('.')?.1.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" => "application/json",
+        "txt" => "text/plain",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        _ => return None,
+    })
+}
+
+fn sniff(body: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\0asm", "application/wasm"),
+    ];
+    if body.len() >= 12 && &body[..4] == b"RIFF" && &body[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    SIGNATURES
+        .iter()
+        .find(|(magic, _)| body.starts_with(magic))
+        .map(|(_, mime)| *mime)
+}
+
+pub fn mime_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+pub fn is_text(content_type: &str) -> bool {
+    let mime = mime_type(content_type);
+    mime.starts_with("text/")
+        || matches!(
+            mime.as_str(),
+            "application/json" | "application/javascript" | "application/xml"
+        )
+        || mime.ends_with("+xml")
+        || mime.ends_with("+json")
+}
+
+pub struct DecodedText {
+    pub text: String,
+    pub encoding: &'static str,
+    pub source: &'static str,
+}
+
+pub fn decode_text(content_type: &str, body: Vec<u8>) -> DecodedText {
+    let html = mime_type(content_type).contains("html");
+    let (encoding, source) = if let Some((enc, _)) = Encoding::for_bom(&body) {
+        (enc, "byte order mark")
+    } else if let Some(enc) = charset_param(content_type).and_then(Encoding::for_label) {
+        (enc, "content-type header")
+    } else if let Some(enc) = meta_charset(&body).filter(|_| html) {
+        (enc, "meta charset")
+    } else {
+        (UTF_8, "default")
+    };
+    // UTF-8 without a byte order mark is taken over as is rather than copied.
+    let body = match String::from_utf8(body) {
+        Ok(text) if encoding == UTF_8 && source != "byte order mark" => {
+            return DecodedText { text, encoding: encoding.name(), source };
+        }
+        Ok(text) => text.into_bytes(),
+        Err(e) => e.into_bytes(),
+    };
+    let (text, encoding, _) = encoding.decode(&body);
+    DecodedText { text: text.into_owned(), encoding: encoding.name(), source }
+}
+
+fn charset_param(content_type: &str) -> Option<&[u8]> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(['"', '\'']).as_bytes())
+    })
+}
+
+fn meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    let head = &body[..body.len().min(1024)];
+    let lower = String::from_utf8_lossy(head).to_ascii_lowercase();
+    let mut rest = lower.as_str();
+    while let Some(pos) = rest.find("<meta") {
+        rest = &rest[pos + 5..];
+        let tag = &rest[..rest.find('>').unwrap_or(rest.len())];
+        if let Some(pos) = tag.find("charset=") {
+            let value = tag[pos + 8..].trim_start_matches(['"', '\'']);
+            let end = value
+                .find(|c: char| c == '"' || c == '\'' || c == ';' || c == '/' || c.is_whitespace())
+                .unwrap_or(value.len());
+            if let Some(enc) = Encoding::for_label(&value.as_bytes()[..end]) {
+                // A meta tag can only be read if the page is ASCII-compatible,
+                // so UTF-16 labels here really mean UTF-8.
+                return Some(if enc == UTF_16LE || enc == UTF_16BE { UTF_8 } else { enc });
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str =
+        "<html><head><meta charset=\"shift_jis\"></head><body>日本語</body></html>";
+
+    fn shift_jis_page() -> Vec<u8> {
+        encoding_rs::SHIFT_JIS.encode(PAGE).0.into_owned()
+    }
+
+    #[test]
+    fn guesses_types_without_a_charset() {
+        assert_eq!(detect_content_type(&[], "web://node1/index.html", b"<p>"), "text/html");
+        assert_eq!(detect_content_type(&[], "web://node1/app.js?v=2", b""), "text/javascript");
+        assert_eq!(detect_content_type(&[], "web://node1/logo", b"GIF89a..."), "image/gif");
+        assert_eq!(detect_content_type(&[], "web://node1/page", b"hello"), "text/html");
+        assert_eq!(
+            detect_content_type(&[], "web://node1/blob", b"\xff\xfe\x00garbage\x9f"),
+            "application/octet-stream",
+        );
+        let headers = [nwep::Header { name: "Content-Type".into(), value: "text/plain".into() }];
+        assert_eq!(detect_content_type(&headers, "web://node1/index.html", b""), "text/plain");
+    }
+
+    #[test]
+    fn decodes_by_meta_charset_when_the_type_was_guessed() {
+        for url in ["web://node1/index.html", "web://node1/page"] {
+            let body = shift_jis_page();
+            let content_type = detect_content_type(&[], url, &body);
+            assert_eq!(content_type, "text/html", "{url}");
+            let decoded = decode_text(&content_type, body);
+            assert_eq!(decoded.text, PAGE, "{url}");
+            assert_eq!(decoded.source, "meta charset");
+        }
+    }
+
+    #[test]
+    fn prefers_the_bom_and_then_the_header() {
+        let decoded = decode_text("text/html", b"\xef\xbb\xbfhi".to_vec());
+        assert_eq!((decoded.text.as_str(), decoded.source), ("hi", "byte order mark"));
+        let decoded = decode_text("text/html; charset=shift_jis", shift_jis_page());
+        assert_eq!((decoded.text.as_str(), decoded.source), (PAGE, "content-type header"));
+        let decoded = decode_text("text/plain", shift_jis_page());
+        assert_eq!((decoded.encoding, decoded.source), ("UTF-8", "default"));
+    }
+}
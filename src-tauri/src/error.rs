@@ -0,0 +1,125 @@
+use std::fmt;
+
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    Network,
+    Crypto,
+    Identity,
+    Protocol,
+    IdentityMismatch,
+    KeyChanged,
+    InvalidUrl,
+    Offline,
+    Cancelled,
+    Internal,
+}
+
+impl ErrorCategory {
+    fn from_label(label: &str) -> Self {
+        match label.to_ascii_lowercase().as_str() {
+            "network" => Self::Network,
+            "crypto" => Self::Crypto,
+            "identity" => Self::Identity,
+            "protocol" => Self::Protocol,
+            _ => Self::Internal,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Network => "network",
+            Self::Crypto => "crypto",
+            Self::Identity => "identity",
+            Self::Protocol => "protocol",
+            Self::IdentityMismatch => "identity_mismatch",
+            Self::KeyChanged => "key_changed",
+            Self::InvalidUrl => "invalid_url",
+            Self::Offline => "offline",
+            Self::Cancelled => "cancelled",
+            Self::Internal => "internal",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NwepError {
+    pub category: ErrorCategory,
+    pub code: i32,
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl NwepError {
+    pub fn new(category: ErrorCategory, code: i32, message: impl Into<String>) -> Self {
+        Self {
+            category,
+            code,
+            message: message.into(),
+            retryable: category == ErrorCategory::Network,
+        }
+    }
+
+    pub fn timed_out(what: &str, after: std::time::Duration) -> Self {
+        Self::new(
+            ErrorCategory::Network,
+            0,
+            format!("{what} timed out after {} ms", after.as_millis()),
+        )
+    }
+
+    pub fn cancelled() -> Self {
+        Self::new(ErrorCategory::Cancelled, 0, "request cancelled")
+    }
+
+    pub fn identity_mismatch(expected: &str, actual: &str) -> Self {
+        Self::new(
+            ErrorCategory::IdentityMismatch,
+            0,
+            format!("expected node {expected}, but the server authenticated as {actual}"),
+        )
+    }
+
+    pub fn key_changed(node_id: &str) -> Self {
+        Self::new(
+            ErrorCategory::KeyChanged,
+            0,
+            format!("node {node_id} presented a different public key than the one pinned on first use"),
+        )
+    }
+
+    pub fn invalid_url(reason: impl Into<String>) -> Self {
+        Self::new(ErrorCategory::InvalidUrl, 0, reason)
+    }
+
+    pub fn offline() -> Self {
+        Self::new(ErrorCategory::Offline, 0, "offline mode is on and no saved copy of this page exists")
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCategory::Internal, 0, message)
+    }
+
+    // nwep formats its errors as `[category:code] message`.
+    pub fn parse(text: &str) -> Self {
+        let parsed = text.strip_prefix('[').and_then(|rest| {
+            let (tag, message) = rest.split_once(']')?;
+            let (category, code) = tag.split_once(':')?;
+            Some((category, code.parse().ok()?, message.trim()))
+        });
+        match parsed {
+            Some((category, code, message)) => {
+                Self::new(ErrorCategory::from_label(category), code, message)
+            }
+            None => Self::internal(text),
+        }
+    }
+}
+
+impl fmt::Display for NwepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}:{}] {}", self.category.label(), self.code, self.message)
+    }
+}
@@ -0,0 +1,213 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+use serde::Serialize;
+
+use crate::url::WebUrl;
+
+pub const MAX_WORKERS: usize = 16;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+type Shared = Arc<(Mutex<State>, Condvar)>;
+
+thread_local! {
+    static CURRENT_SLOT: RefCell<Option<Arc<Slot>>> = const { RefCell::new(None) };
+}
+
+#[derive(Serialize)]
+pub struct NodeStats {
+    node_id: String,
+    active: usize,
+    queued: usize,
+}
+
+#[derive(Serialize)]
+pub struct ExecutorStats {
+    limit: usize,
+    active: usize,
+    queued: usize,
+    nodes: Vec<NodeStats>,
+}
+
+struct State {
+    limit: usize,
+    active: usize,
+    queues: HashMap<String, VecDeque<Job>>,
+    // Nodes with queued work, in the order they will next be served.
+    turns: VecDeque<String>,
+    running: HashMap<String, usize>,
+}
+
+impl State {
+    fn next_job(&mut self) -> Option<(String, Job)> {
+        if self.active >= self.limit {
+            return None;
+        }
+        let node = self.turns.pop_front()?;
+        let queue = self.queues.get_mut(&node)?;
+        let job = queue.pop_front()?;
+        if queue.is_empty() {
+            self.queues.remove(&node);
+        } else {
+            self.turns.push_back(node.clone());
+        }
+        Some((node, job))
+    }
+}
+
+// Runs nwep work on a fixed set of threads. At most `limit` jobs run at once,
+// and queued jobs are taken round-robin per node so one busy node cannot
+// starve the others.
+#[derive(Clone)]
+pub struct FetchExecutor {
+    shared: Shared,
+}
+
+// A place taken under the limit. It is given back once every clone is gone,
+// so work a job leaves running on another thread keeps it taken.
+pub struct Slot {
+    shared: Shared,
+    node: String,
+    calls: AtomicUsize,
+}
+
+impl Slot {
+    // Counts a blocking call made on a thread of its own until the `Call` is
+    // dropped, which also keeps the slot taken.
+    pub fn begin_call(self: Arc<Self>) -> Call {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Call(self)
+    }
+
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+pub struct Call(Arc<Slot>);
+
+impl Drop for Call {
+    fn drop(&mut self) {
+        self.0.calls.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let (state, ready) = &*self.shared;
+        let mut state = state.lock().unwrap();
+        state.active -= 1;
+        if let Some(count) = state.running.get_mut(&self.node) {
+            *count -= 1;
+            if *count == 0 {
+                state.running.remove(&self.node);
+            }
+        }
+        ready.notify_all();
+    }
+}
+
+// The slot of the job running on this thread, if any.
+pub fn current_slot() -> Option<Arc<Slot>> {
+    CURRENT_SLOT.with(|slot| slot.borrow().clone())
+}
+
+impl FetchExecutor {
+    pub fn new(limit: usize) -> Self {
+        let state = State {
+            limit: limit.clamp(1, MAX_WORKERS),
+            active: 0,
+            queues: HashMap::new(),
+            turns: VecDeque::new(),
+            running: HashMap::new(),
+        };
+        let executor = Self { shared: Arc::new((Mutex::new(state), Condvar::new())) };
+        for _ in 0..MAX_WORKERS {
+            let executor = executor.clone();
+            std::thread::spawn(move || executor.work());
+        }
+        executor
+    }
+
+    pub fn set_limit(&self, limit: usize) {
+        let (state, ready) = &*self.shared;
+        state.lock().unwrap().limit = limit.clamp(1, MAX_WORKERS);
+        ready.notify_all();
+    }
+
+    pub fn submit(&self, node: String, job: impl FnOnce() + Send + 'static) {
+        let (state, ready) = &*self.shared;
+        let mut state = state.lock().unwrap();
+        if !state.queues.contains_key(&node) {
+            state.turns.push_back(node.clone());
+        }
+        state.queues.entry(node).or_default().push_back(Box::new(job));
+        ready.notify_one();
+    }
+
+    pub async fn run<T, F>(&self, node: String, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (tx, mut rx) = tauri::async_runtime::channel(1);
+        self.submit(node, move || {
+            let _ = tx.blocking_send(f());
+        });
+        rx.recv().await.ok_or_else(|| "fetch worker stopped".to_string())
+    }
+
+    pub fn stats(&self) -> ExecutorStats {
+        let state = self.shared.0.lock().unwrap();
+        let mut nodes: Vec<NodeStats> = state
+            .queues
+            .keys()
+            .chain(state.running.keys())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .map(|node| NodeStats {
+                node_id: node.clone(),
+                active: state.running.get(node).copied().unwrap_or_default(),
+                queued: state.queues.get(node).map_or(0, VecDeque::len),
+            })
+            .collect();
+        nodes.sort_by_key(|n| std::cmp::Reverse(n.active + n.queued));
+        ExecutorStats {
+            limit: state.limit,
+            active: state.active,
+            queued: state.queues.values().map(VecDeque::len).sum(),
+            nodes,
+        }
+    }
+
+    fn work(&self) {
+        let (state, ready) = &*self.shared;
+        let mut guard = state.lock().unwrap();
+        loop {
+            let Some((node, job)) = guard.next_job() else {
+                guard = ready.wait(guard).unwrap();
+                continue;
+            };
+            guard.active += 1;
+            *guard.running.entry(node.clone()).or_default() += 1;
+            drop(guard);
+
+            let slot =
+                Arc::new(Slot { shared: self.shared.clone(), node, calls: AtomicUsize::new(0) });
+            CURRENT_SLOT.with(|current| *current.borrow_mut() = Some(slot));
+            let _ = panic::catch_unwind(AssertUnwindSafe(job));
+            CURRENT_SLOT.with(|current| current.borrow_mut().take());
+
+            guard = state.lock().unwrap();
+        }
+    }
+}
+
+// Unparseable URLs share one queue; they fail as soon as they run.
+pub fn node_key(url: &str) -> String {
+    WebUrl::parse(url).map(|u| u.node_id().to_string()).unwrap_or_default()
+}
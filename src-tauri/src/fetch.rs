@@ -0,0 +1,733 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::cache::{self, Lookup, OfflineMode, ResponseCache};
+use crate::cancel::CancelToken;
+use crate::compression;
+use crate::content;
+use crate::error::{ErrorCategory, NwepError};
+use crate::history::HistoryStore;
+use crate::identity::{self, IdentityStore};
+use crate::known_nodes::{KeyChange, KnownNodes, PinCheck};
+use crate::links;
+use crate::policy::{self, NetworkPolicy, NetworkSettings, Wait};
+use crate::pool::ConnectionPool;
+use crate::progress::{FetchEvent, StepLog};
+use crate::text;
+use crate::to_hex;
+use crate::url::WebUrl;
+
+#[derive(Serialize, Deserialize)]
+pub struct NwepHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct LogStep {
+    name: String,
+    ok: bool,
+    detail: Option<String>,
+    error: Option<NwepError>,
+    pub offset_ms: f64,
+    pub duration_ms: f64,
+}
+
+impl LogStep {
+    pub fn ok(name: &str, detail: Option<String>) -> Self {
+        Self {
+            name: name.into(),
+            ok: true,
+            detail,
+            error: None,
+            offset_ms: 0.0,
+            duration_ms: 0.0,
+        }
+    }
+
+    pub fn failed(name: &str, error: &NwepError) -> Self {
+        Self {
+            name: name.into(),
+            ok: false,
+            detail: Some(error.to_string()),
+            error: Some(error.clone()),
+            offset_ms: 0.0,
+            duration_ms: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ConnectionInfo {
+    client_node_id: String,
+    server_node_id: String,
+    server_pubkey: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct StaleInfo {
+    age_secs: u64,
+    error: Option<NwepError>,
+}
+
+pub struct Exchange {
+    pub response: nwep::Response,
+    pub connection: ConnectionInfo,
+    pub url: WebUrl,
+    pub stale: Option<StaleInfo>,
+}
+
+#[derive(Serialize)]
+pub struct NwepResult {
+    ok: bool,
+    error: Option<NwepError>,
+    url: Option<String>,
+    status: Option<String>,
+    status_details: Option<String>,
+    content_type: Option<String>,
+    text: Option<String>,
+    body_base64: Option<String>,
+    headers: Vec<NwepHeader>,
+    connection: Option<ConnectionInfo>,
+    key_change: Option<KeyChange>,
+    stale: Option<StaleInfo>,
+    cancelled: bool,
+    ttfb_ms: Option<f64>,
+    total_ms: f64,
+    log: Vec<LogStep>,
+}
+
+pub struct FetchContext {
+    pub pool: ConnectionPool,
+    pub known: KnownNodes,
+    pub cache: Option<ResponseCache>,
+    // Set when page text should be indexed for history search.
+    pub history: Option<HistoryStore>,
+    pub offline: bool,
+    pub policy: NetworkPolicy,
+    pub seed: [u8; 32],
+    pub cancel: CancelToken,
+    pub progress: Option<Channel<FetchEvent>>,
+}
+
+impl FetchContext {
+    pub fn from_app<R: Runtime>(
+        app: &AppHandle<R>,
+        cancel: CancelToken,
+        progress: Option<Channel<FetchEvent>>,
+    ) -> Self {
+        Self {
+            pool: app.state::<ConnectionPool>().inner().clone(),
+            known: app.state::<KnownNodes>().inner().clone(),
+            cache: Some(app.state::<ResponseCache>().inner().clone()),
+            history: Some(app.state::<HistoryStore>().inner().clone()).filter(|h| h.is_enabled()),
+            offline: app.state::<OfflineMode>().get(),
+            policy: app.state::<NetworkSettings>().get(),
+            seed: app.state::<IdentityStore>().seed(),
+            cancel,
+            progress,
+        }
+    }
+}
+
+type FetchError = (NwepError, Option<ConnectionInfo>);
+
+// What is sent to each address on the way to the final response.
+#[derive(Clone)]
+pub struct RequestSpec {
+    pub method: String,
+    pub headers: Vec<nwep::Header>,
+    pub body: Vec<u8>,
+}
+
+impl RequestSpec {
+    pub fn get() -> Self {
+        Self { method: "GET".into(), headers: Vec::new(), body: Vec::new() }
+    }
+
+    // Only these can be sent again without the node acting on them twice.
+    fn idempotent(&self) -> bool {
+        matches!(self.method.as_str(), "GET" | "HEAD")
+    }
+
+    // A 303 is always followed with a GET. A 301 or 302 answering a POST is
+    // too, as browsers do; anything else is resent unchanged.
+    fn redirected(&self, status: &str) -> Self {
+        let as_get = match status.to_ascii_lowercase().as_str() {
+            "303" | "see_other" => self.method != "HEAD",
+            "301" | "302" | "moved_permanently" | "found" | "redirect" => self.method == "POST",
+            _ => false,
+        };
+        if !as_get {
+            return self.clone();
+        }
+        let headers = self
+            .headers
+            .iter()
+            .filter(|h| !h.name.to_ascii_lowercase().starts_with("content-"))
+            .cloned()
+            .collect();
+        Self { method: "GET".into(), headers, body: Vec::new() }
+    }
+}
+
+const MAX_REDIRECTS: usize = 10;
+
+fn failed(
+    ctx: &FetchContext,
+    error: NwepError,
+    connection: Option<ConnectionInfo>,
+    log: StepLog,
+) -> NwepResult {
+    let key_change = match (&error.category, &connection) {
+        (ErrorCategory::KeyChanged, Some(conn)) => {
+            ctx.known.key_change(&conn.server_node_id, &conn.server_pubkey)
+        }
+        _ => None,
+    };
+    NwepResult {
+        ok: false, error: Some(error), url: None,
+        status: None, status_details: None,
+        content_type: None, text: None, body_base64: None,
+        headers: vec![], connection, key_change, stale: None,
+        cancelled: ctx.cancel.is_cancelled(),
+        ttfb_ms: log.first_byte_ms(), total_ms: log.elapsed_ms(),
+        log: log.into_steps(),
+    }
+}
+
+fn check_cancelled(ctx: &FetchContext, log: &mut StepLog) -> Result<(), NwepError> {
+    if ctx.cancel.is_cancelled() {
+        let error = NwepError::cancelled();
+        log.push(LogStep::failed("request cancelled", &error));
+        return Err(error);
+    }
+    Ok(())
+}
+
+fn connect(ctx: &FetchContext, url: &WebUrl, log: &mut StepLog) -> Result<nwep::Client, NwepError> {
+    check_cancelled(ctx, log)?;
+    let keypair = match identity::keypair_from_seed(&ctx.seed) {
+        Ok(kp) => {
+            log.push(LogStep::ok("loaded client identity", None));
+            kp
+        }
+        Err(e) => {
+            let error = NwepError::parse(&e);
+            log.push(LogStep::failed("loaded client identity", &error));
+            return Err(error);
+        }
+    };
+
+    check_cancelled(ctx, log)?;
+    let target = url.without_fragment();
+    log.emit(FetchEvent::Connecting { url: target.clone() });
+    let timeout = ctx.policy.connect_timeout();
+    let result = policy::wait_for(timeout, &ctx.cancel, move || {
+        nwep::ClientBuilder::new()
+            .connect(keypair, &target)
+            .map_err(|e| NwepError::parse(&format!("{e}")))
+    });
+    match waited(ctx, result, "connect", timeout, log) {
+        Ok(c) => {
+            log.push(LogStep::ok("client established connection", None));
+            Ok(c)
+        }
+        Err(error) => {
+            log.push(LogStep::failed("client established connection", &error));
+            Err(error)
+        }
+    }
+}
+
+fn waited<T>(
+    ctx: &FetchContext,
+    result: Wait<Result<T, NwepError>>,
+    what: &str,
+    timeout: Duration,
+    log: &mut StepLog,
+) -> Result<T, NwepError> {
+    match result {
+        Wait::Done(result) => result,
+        Wait::TimedOut => Err(NwepError::timed_out(what, timeout)),
+        Wait::Cancelled => {
+            check_cancelled(ctx, log)?;
+            Err(NwepError::cancelled())
+        }
+    }
+}
+
+// A failure comes with whether the request timed out, after which it may
+// already have reached the node.
+fn send_request(
+    ctx: &FetchContext,
+    request: &Arc<RequestSpec>,
+    client: &Arc<nwep::Client>,
+    path: &str,
+    headers: &[nwep::Header],
+    log: &mut StepLog,
+) -> Result<nwep::Response, (NwepError, bool)> {
+    let timeout = ctx.policy.request_timeout();
+    let (request, client) = (request.clone(), client.clone());
+    let (path, headers) = (path.to_string(), headers.to_vec());
+    let result = policy::wait_for(timeout, &ctx.cancel, move || {
+        client
+            .request(&request.method, &path, &headers, &request.body)
+            .map_err(|e| NwepError::parse(&format!("{e}")))
+    });
+    let timed_out = matches!(result, Wait::TimedOut);
+    waited(ctx, result, "request", timeout, log).map_err(|e| (e, timed_out))
+}
+
+// Retries network failures with backoff, logging each attempt as its own step.
+fn with_retries<T>(
+    ctx: &FetchContext,
+    log: &mut StepLog,
+    mut attempt: impl FnMut(&mut StepLog) -> Result<T, FetchError>,
+) -> Result<T, FetchError> {
+    let mut n = 1;
+    loop {
+        let name = format!("attempt {n} of {}", ctx.policy.retries + 1);
+        match attempt(log) {
+            Ok(value) => {
+                log.push(LogStep::ok(&name, None));
+                return Ok(value);
+            }
+            Err((e, connection)) => {
+                log.push(LogStep::failed(&name, &e));
+                if !e.retryable || n > ctx.policy.retries {
+                    return Err((e, connection));
+                }
+                let delay = ctx.policy.backoff(n);
+                if !policy::sleep(delay, &ctx.cancel) {
+                    let error = NwepError::cancelled();
+                    log.push(LogStep::failed("request cancelled", &error));
+                    return Err((error, connection));
+                }
+                let detail = format!("{} ms", delay.as_millis());
+                log.push(LogStep::ok("waited before retrying", Some(detail)));
+                // A timed out attempt may still be running; one at a time per slot.
+                match policy::settle(ctx.policy.request_timeout(), &ctx.cancel) {
+                    Wait::Done(()) => {}
+                    Wait::TimedOut => {
+                        log.push(LogStep::failed("earlier attempt still running", &e));
+                        return Err((e, connection));
+                    }
+                    Wait::Cancelled => {
+                        let error = NwepError::cancelled();
+                        log.push(LogStep::failed("request cancelled", &error));
+                        return Err((error, connection));
+                    }
+                }
+                n += 1;
+            }
+        }
+    }
+}
+
+fn pooled_client(
+    ctx: &FetchContext,
+    url: &WebUrl,
+    log: &mut StepLog,
+) -> Result<(Arc<nwep::Client>, bool), NwepError> {
+    let key = url.authority();
+    if let Some(client) = ctx.pool.get(&key) {
+        log.push(LogStep::ok("reused pooled connection", Some(key)));
+        log.emit(FetchEvent::Connected { reused: true });
+        return Ok((client, true));
+    }
+    let client = Arc::new(connect(ctx, url, log)?);
+    ctx.pool.insert(key, client.clone());
+    log.emit(FetchEvent::Connected { reused: false });
+    Ok((client, false))
+}
+
+fn verify_peer(client: &nwep::Client, url: &WebUrl, log: &mut StepLog) -> Result<(), NwepError> {
+    // An exact match: the URL's node id is kept as written (see `parse_authority`).
+    let actual = client.peer_identity().node_id.to_string();
+    if url.node_id() == actual {
+        log.push(LogStep::ok("verified node id matches URL", Some(actual)));
+        return Ok(());
+    }
+    let error = NwepError::identity_mismatch(url.node_id(), &actual);
+    log.push(LogStep::failed("verified node id matches URL", &error));
+    Err(error)
+}
+
+fn check_pin(
+    ctx: &FetchContext,
+    client: &nwep::Client,
+    log: &mut StepLog,
+) -> Result<(), NwepError> {
+    let node_id = client.peer_node_id().to_string();
+    let pubkey = to_hex(&client.peer_identity().pubkey);
+    match ctx.known.check(&node_id, &pubkey) {
+        Ok(PinCheck::FirstUse) => {
+            let detail = match ctx.known.replaced() {
+                Some(aside) => format!(
+                    "{pubkey} (earlier pins were lost; the unreadable file is at {})",
+                    aside.display()
+                ),
+                None => pubkey,
+            };
+            log.push(LogStep::ok("pinned server key on first use", Some(detail)));
+            Ok(())
+        }
+        Ok(PinCheck::Matches) => {
+            log.push(LogStep::ok("server key matches pinned key", Some(pubkey)));
+            Ok(())
+        }
+        Ok(PinCheck::Changed) => {
+            let error = NwepError::key_changed(&node_id);
+            log.push(LogStep::failed("server key matches pinned key", &error));
+            Err(error)
+        }
+        Err(e) => {
+            let error = NwepError::internal(e);
+            log.push(LogStep::failed("server key matches pinned key", &error));
+            Err(error)
+        }
+    }
+}
+
+fn connection_info(client: &nwep::Client) -> ConnectionInfo {
+    let peer = client.peer_identity();
+    ConnectionInfo {
+        client_node_id: client.node_id().to_string(),
+        server_node_id: client.peer_node_id().to_string(),
+        server_pubkey: to_hex(&peer.pubkey),
+    }
+}
+
+fn decompress(
+    mut resp: nwep::Response,
+    method: &str,
+    log: &mut StepLog,
+) -> Result<nwep::Response, NwepError> {
+    // The coding of a HEAD answer describes the body a GET would get.
+    let encoding = match content::header(&resp.headers, "content-encoding") {
+        Some(e) if method != "HEAD" && !e.trim().eq_ignore_ascii_case("identity") => e.to_string(),
+        _ => return Ok(resp),
+    };
+    let compressed = resp.body.len();
+    match compression::decode(&encoding, std::mem::take(&mut resp.body)) {
+        Ok(body) => {
+            log.push(LogStep::ok(
+                "decompressed body",
+                Some(format!("{encoding}: {compressed} → {} bytes", body.len())),
+            ));
+            resp.body = body;
+            resp.headers.retain(|h| {
+                !h.name.eq_ignore_ascii_case("content-encoding")
+                    && !h.name.eq_ignore_ascii_case("content-length")
+            });
+            Ok(resp)
+        }
+        Err(e) => {
+            let error = NwepError::new(ErrorCategory::Protocol, 0, e);
+            log.push(LogStep::failed("decompressed body", &error));
+            Err(error)
+        }
+    }
+}
+
+fn exchange_once(
+    ctx: &FetchContext,
+    url: &WebUrl,
+    log: &mut StepLog,
+    request: &Arc<RequestSpec>,
+    validators: &[nwep::Header],
+) -> Result<(nwep::Response, ConnectionInfo), FetchError> {
+    let path = url.request_target();
+    let headers = [&request.headers, validators, &[compression::accept_encoding()]].concat();
+
+    let (mut client, reused) = pooled_client(ctx, url, log).map_err(|e| (e, None))?;
+    let verify = |client: &nwep::Client, log: &mut StepLog| {
+        verify_peer(client, url, log)
+            .and_then(|()| check_pin(ctx, client, log))
+            .map_err(|e| {
+                ctx.pool.remove(&url.authority());
+                (e, Some(connection_info(client)))
+            })
+    };
+    verify(&client, log)?;
+
+    check_cancelled(ctx, log).map_err(|e| (e, None))?;
+    let mut result = send_request(ctx, request, &client, &path, &headers, log);
+    if reused && request.idempotent() {
+        if let Err((e, false)) = &result {
+            log.push(LogStep::failed("pooled connection failed", e));
+            ctx.pool.remove(&url.authority());
+            client = pooled_client(ctx, url, log).map_err(|e| (e, None))?.0;
+            verify(&client, log)?;
+            check_cancelled(ctx, log).map_err(|e| (e, None))?;
+            result = send_request(ctx, request, &client, &path, &headers, log);
+        }
+    }
+    // Once sent, anything but GET and HEAD is left to the caller to repeat.
+    let result = result.map_err(|(mut e, _)| {
+        e.retryable &= request.idempotent();
+        e
+    });
+    if result.is_ok() {
+        log.mark_first_byte();
+    }
+
+    let connection = connection_info(&client);
+
+    match result {
+        Ok(r) => {
+            log.emit(FetchEvent::HeadersReceived {
+                status: r.status.clone(),
+                header_count: r.headers.len(),
+            });
+            let detail = if r.status_details.is_empty() {
+                r.status.clone()
+            } else {
+                format!("{} {}", r.status, r.status_details)
+            };
+            log.push(LogStep::ok("fetched resource", Some(detail)));
+            let r = match decompress(r, &request.method, log) {
+                Ok(r) => r,
+                Err(e) => return Err((e, Some(connection))),
+            };
+            match check_cancelled(ctx, log) {
+                Ok(()) => Ok((r, connection)),
+                Err(e) => Err((e, Some(connection))),
+            }
+        }
+        Err(e) => {
+            log.push(LogStep::failed("fetched resource", &e));
+            ctx.pool.remove(&url.authority());
+            Err((e, Some(connection)))
+        }
+    }
+}
+
+// Errors that mean the node could not be reached, as opposed to ones the
+// user has to act on, are answered with the last saved copy when there is one.
+fn can_fall_back(error: &NwepError) -> bool {
+    !matches!(
+        error.category,
+        ErrorCategory::Cancelled
+            | ErrorCategory::InvalidUrl
+            | ErrorCategory::IdentityMismatch
+            | ErrorCategory::KeyChanged
+    )
+}
+
+fn saved_copy(
+    cache: &ResponseCache,
+    key: &str,
+    error: Option<NwepError>,
+    log: &mut StepLog,
+) -> Option<(nwep::Response, ConnectionInfo, Option<StaleInfo>)> {
+    let (resp, connection, age_secs) = cache.last_good(key)?;
+    log.mark_first_byte();
+    log.push(LogStep::ok("response cache", Some(format!("saved copy, {age_secs}s old"))));
+    Some((resp, connection, Some(StaleInfo { age_secs, error })))
+}
+
+fn cached_exchange(
+    ctx: &FetchContext,
+    url: &WebUrl,
+    log: &mut StepLog,
+    request: &Arc<RequestSpec>,
+) -> Result<(nwep::Response, ConnectionInfo, Option<StaleInfo>), FetchError> {
+    let Some(cache) = &ctx.cache else {
+        if ctx.offline {
+            let error = NwepError::offline();
+            log.push(LogStep::failed("response cache", &error));
+            return Err((error, None));
+        }
+        return with_retries(ctx, log, |log| exchange_once(ctx, url, log, request, &[]))
+            .map(|(r, c)| (r, c, None));
+    };
+    let key = url.without_fragment();
+    let validators = match cache.lookup(&key) {
+        Lookup::Fresh(resp, connection) => {
+            log.mark_first_byte();
+            log.push(LogStep::ok("response cache", Some("fresh".into())));
+            return Ok((resp, connection, None));
+        }
+        Lookup::Stale(validators) => validators,
+        Lookup::Miss => Vec::new(),
+    };
+
+    if ctx.offline {
+        return saved_copy(cache, &key, None, log).ok_or_else(|| {
+            let error = NwepError::offline();
+            log.push(LogStep::failed("response cache", &error));
+            (error, None)
+        });
+    }
+
+    let attempt = with_retries(ctx, log, |log| exchange_once(ctx, url, log, request, &validators));
+    let (resp, connection) = match attempt {
+        Ok(r) => r,
+        Err((e, connection)) if can_fall_back(&e) => {
+            return saved_copy(cache, &key, Some(e.clone()), log).ok_or((e, connection));
+        }
+        Err(e) => return Err(e),
+    };
+    if let Some((resp, connection)) = cache.revalidate(&key, &resp) {
+        log.push(LogStep::ok("response cache", Some("revalidated".into())));
+        return Ok((resp, connection, None));
+    }
+    cache.store(&key, &resp, &connection);
+    log.push(LogStep::ok("response cache", Some("network".into())));
+    Ok((resp, connection, None))
+}
+
+fn is_redirect(status: &str) -> bool {
+    matches!(
+        status.to_ascii_lowercase().as_str(),
+        "301" | "302" | "303" | "307" | "308"
+            | "redirect" | "moved_permanently" | "found" | "see_other"
+            | "temporary_redirect" | "permanent_redirect"
+    )
+}
+
+fn redirect_error(message: String) -> NwepError {
+    NwepError::new(ErrorCategory::Protocol, 0, message)
+}
+
+// Follows redirect statuses that carry a `location` header and returns the
+// final response along with the URL it was served from.
+pub fn exchange(
+    ctx: &FetchContext,
+    url: &WebUrl,
+    log: &mut StepLog,
+    request: RequestSpec,
+) -> Result<Exchange, FetchError> {
+    let mut request = Arc::new(request);
+    let mut url = url.clone();
+    let mut visited = vec![url.without_fragment()];
+    loop {
+        let (resp, connection, stale) = cached_exchange(ctx, &url, log, &request)?;
+        let location = match content::header(&resp.headers, "location") {
+            Some(location) if is_redirect(&resp.status) => location,
+            _ => return Ok(Exchange { response: resp, connection, url, stale }),
+        };
+
+        let next = match url.join(location) {
+            Some(Ok(mut next)) => {
+                next.inherit_fragment(&url);
+                next
+            }
+            _ => {
+                let error = redirect_error(format!("invalid redirect location `{location}`"));
+                log.push(LogStep::failed("followed redirect", &error));
+                return Err((error, Some(connection)));
+            }
+        };
+        let error = if visited.contains(&next.without_fragment()) {
+            Some(redirect_error(format!("redirect loop at {next}")))
+        } else if visited.len() > MAX_REDIRECTS {
+            Some(redirect_error(format!("more than {MAX_REDIRECTS} redirects")))
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            log.push(LogStep::failed("followed redirect", &error));
+            return Err((error, Some(connection)));
+        }
+        let next_request = request.redirected(&resp.status);
+        let detail = if next_request.method == request.method {
+            format!("{} → {next}", resp.status)
+        } else {
+            format!("{} → {} {next}", resp.status, next_request.method)
+        };
+        log.push(LogStep::ok("followed redirect", Some(detail)));
+        visited.push(next.without_fragment());
+        url = next;
+        request = Arc::new(next_request);
+    }
+}
+
+fn index_text(ctx: &FetchContext, url: &WebUrl, html: bool, source: &str, log: &mut StepLog) {
+    let Some(history) = &ctx.history else {
+        return;
+    };
+    let page = if html {
+        text::extract(source)
+    } else {
+        text::PageText { title: None, body: text::plain(source) }
+    };
+    match history.index_page(url, page.title.as_deref(), &page.body) {
+        Ok(()) => log.push(LogStep::ok("indexed text", Some(format!("{} chars", page.body.len())))),
+        Err(e) => log.push(LogStep::failed("indexed text", &NwepError::internal(e))),
+    }
+}
+
+pub fn execute(ctx: &FetchContext, url: &str, request: RequestSpec) -> NwepResult {
+    let mut log = StepLog::new(ctx.progress.clone());
+    let url = match WebUrl::parse(url) {
+        Ok(url) => {
+            log.push(LogStep::ok("parsed URL", Some(url.to_string())));
+            url
+        }
+        Err(e) => {
+            let error = NwepError::invalid_url(e);
+            log.push(LogStep::failed("parsed URL", &error));
+            return failed(ctx, error, None, log);
+        }
+    };
+    let exchanged = match exchange(ctx, &url, &mut log, request) {
+        Ok(r) => r,
+        Err((e, connection)) => return failed(ctx, e, connection, log),
+    };
+    let Exchange { response: mut resp, connection, url, stale } = exchanged;
+
+    let content_type = content::detect_content_type(&resp.headers, &url.to_string(), &resp.body);
+    log.push(LogStep::ok(
+        "received body",
+        Some(format!("{} bytes, {content_type}", resp.body.len())),
+    ));
+
+    // The body is handed on rather than copied, so it is only held once.
+    let body = std::mem::take(&mut resp.body);
+    let (text, body_base64) = if content::is_text(&content_type) {
+        let decoded = content::decode_text(&content_type, body);
+        log.push(LogStep::ok(
+            "decoded text",
+            Some(format!("{} (from {})", decoded.encoding, decoded.source)),
+        ));
+        let html = content::mime_type(&content_type) == "text/html";
+        if cache::is_success(&resp.status) {
+            index_text(ctx, &url, html, &decoded.text, &mut log);
+        }
+        let text = if html { links::rewrite_links(&decoded.text, &url) } else { decoded.text };
+        (Some(text), None)
+    } else {
+        (None, Some(base64::engine::general_purpose::STANDARD.encode(body)))
+    };
+
+    NwepResult {
+        ok: true,
+        error: None,
+        url: Some(url.to_string()),
+        status: Some(resp.status),
+        status_details: Some(resp.status_details),
+        content_type: Some(content_type),
+        text,
+        body_base64,
+        headers: resp
+            .headers
+            .into_iter()
+            .map(|h| NwepHeader { name: h.name, value: h.value })
+            .collect(),
+        connection: Some(connection),
+        key_change: None,
+        stale,
+        cancelled: false,
+        ttfb_ms: log.first_byte_ms(),
+        total_ms: log.elapsed_ms(),
+        log: log.into_steps(),
+    }
+}
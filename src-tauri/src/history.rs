@@ -0,0 +1,527 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{set_aside, unix_now_ms};
+use crate::url::WebUrl;
+
+const PAGE_SIZE: u32 = 100;
+const MAX_PAGE_SIZE: u32 = 1000;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const PRUNE_INTERVAL_MS: i64 = 60 * 60 * 1000;
+const SEARCH_LIMIT: u32 = 50;
+
+// Each entry moves the schema up one version; `user_version` counts how many have run.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE visits (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        url TEXT NOT NULL,
+        title TEXT NOT NULL DEFAULT '',
+        visited_at INTEGER NOT NULL,
+        transition TEXT NOT NULL,
+        tab_id TEXT
+    );
+    CREATE INDEX visits_visited_at ON visits (visited_at);
+    CREATE INDEX visits_url ON visits (url);
+    ",
+    // One row per page (URL without fragment), pointing at its latest visit.
+    "
+    CREATE VIRTUAL TABLE page_text USING fts5 (
+        url UNINDEXED,
+        visit_id UNINDEXED,
+        title,
+        body,
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    ",
+];
+
+// How the user got to a page.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transition {
+    Link,
+    Typed,
+    BackForward,
+    Reload,
+    StartPage,
+    Imported,
+}
+
+impl Transition {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Link => "link",
+            Self::Typed => "typed",
+            Self::BackForward => "back_forward",
+            Self::Reload => "reload",
+            Self::StartPage => "start_page",
+            Self::Imported => "imported",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "typed" => Self::Typed,
+            "back_forward" => Self::BackForward,
+            "reload" => Self::Reload,
+            "start_page" => Self::StartPage,
+            "imported" => Self::Imported,
+            _ => Self::Link,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewVisit {
+    url: String,
+    #[serde(default)]
+    title: String,
+    transition: Transition,
+    tab_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LegacyEntry {
+    url: String,
+    #[serde(default)]
+    title: String,
+    timestamp: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct Visit {
+    id: i64,
+    url: String,
+    title: String,
+    visited_at: i64,
+    transition: Transition,
+    tab_id: Option<String>,
+}
+
+// Pages run newest first. `before` is the `next_cursor` of the previous page;
+// `from` and `to` bound `visited_at` in milliseconds.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    before: Option<i64>,
+    limit: Option<u32>,
+    from: Option<i64>,
+    to: Option<i64>,
+    text: Option<String>,
+}
+
+// How much a URL has been used: visits weighted by how recent they are and how
+// the user got there.
+pub struct PageStats {
+    pub url: String,
+    pub title: String,
+    pub frecency: f64,
+}
+
+#[derive(Serialize)]
+pub struct HistoryPage {
+    visits: Vec<Visit>,
+    next_cursor: Option<i64>,
+}
+
+// `snippet` marks each matched term with U+E000 before and U+E001 after it.
+#[derive(Serialize)]
+pub struct PageMatch {
+    url: String,
+    title: String,
+    snippet: String,
+    visit_id: Option<i64>,
+    visited_at: Option<i64>,
+    score: f64,
+}
+
+struct Db {
+    conn: Connection,
+    retention_days: u32,
+    last_prune: i64,
+}
+
+impl Db {
+    fn prune(&mut self, now: i64) -> Result<usize, String> {
+        self.last_prune = now;
+        if self.retention_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = now - i64::from(self.retention_days) * DAY_MS;
+        let pruned = self
+            .conn
+            .execute("DELETE FROM visits WHERE visited_at < ?1", [cutoff])
+            .map_err(db_err)?;
+        self.drop_orphaned_text()?;
+        Ok(pruned)
+    }
+
+    // Indexed text belongs to the visit that fetched it and goes with it.
+    fn drop_orphaned_text(&self) -> Result<(), String> {
+        self.conn
+            .execute(
+                "DELETE FROM page_text
+                 WHERE visit_id IS NULL OR visit_id NOT IN (SELECT id FROM visits)",
+                [],
+            )
+            .map(|_| ())
+            .map_err(db_err)
+    }
+}
+
+// Every visit, kept in SQLite until it ages out of the retention window,
+// along with a full-text index of the pages visited.
+#[derive(Clone)]
+pub struct HistoryStore {
+    db: Arc<Mutex<Db>>,
+    enabled: Arc<AtomicBool>,
+    revision: Arc<AtomicU64>,
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+        }
+        Self::with_connection(Connection::open(path).map_err(db_err)?)
+    }
+
+    // A database that can't be opened is moved aside, with its journal files, and
+    // a fresh one started in its place. Returns where the old one went.
+    pub fn open_or_replace(path: &Path) -> Result<(Self, Option<PathBuf>), String> {
+        match Self::open(path) {
+            Ok(store) => Ok((store, None)),
+            Err(_) if path.exists() => {
+                let aside = set_aside(path)?;
+                for suffix in ["-wal", "-shm"] {
+                    let sidecar = PathBuf::from(format!("{}{suffix}", path.display()));
+                    if sidecar.exists() {
+                        let moved = PathBuf::from(format!("{}{suffix}", aside.display()));
+                        fs::rename(&sidecar, &moved).map_err(|e| {
+                            format!("failed to move {} aside: {e}", sidecar.display())
+                        })?;
+                    }
+                }
+                Ok((Self::open(path)?, Some(aside)))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // History that lasts only as long as the process, for when no file can be used.
+    pub fn in_memory() -> Result<Self, String> {
+        Self::with_connection(Connection::open_in_memory().map_err(db_err)?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, String> {
+        conn.execute_batch("PRAGMA journal_mode = WAL;").map_err(db_err)?;
+        migrate(&conn)?;
+        Ok(Self {
+            db: Arc::new(Mutex::new(Db { conn, retention_days: 0, last_prune: 0 })),
+            enabled: Arc::new(AtomicBool::new(false)),
+            revision: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    // Bumped by changes other than new visits, which show up in
+    // `last_visit_id` instead.
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::Relaxed)
+    }
+
+    fn changed(&self) {
+        self.revision.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn record(&self, visit: NewVisit) -> Result<i64, String> {
+        // Kept in the canonical form, which is also how the fetch keys the
+        // text it indexed for the page.
+        let (url, page) = match WebUrl::parse(&visit.url) {
+            Ok(parsed) => (parsed.to_string(), parsed.without_fragment()),
+            Err(_) => {
+                let page = visit.url.split('#').next().unwrap_or_default().to_string();
+                (visit.url, page)
+            }
+        };
+        let mut db = self.db.lock().unwrap();
+        let now = unix_now_ms() as i64;
+        db.conn
+            .execute(
+                "INSERT INTO visits (url, title, visited_at, transition, tab_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![url, visit.title, now, visit.transition.as_str(), visit.tab_id],
+            )
+            .map_err(db_err)?;
+        let id = db.conn.last_insert_rowid();
+        db.conn
+            .execute("UPDATE page_text SET visit_id = ?1 WHERE url = ?2", params![id, page])
+            .map_err(db_err)?;
+        if now - db.last_prune > PRUNE_INTERVAL_MS && db.prune(now)? > 0 {
+            self.changed();
+        }
+        Ok(id)
+    }
+
+    pub fn query(&self, query: HistoryQuery) -> Result<HistoryPage, String> {
+        let limit = query.limit.unwrap_or(PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize;
+        let pattern = query
+            .text
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(|t| format!("%{}%", escape_like(t)));
+        let db = self.db.lock().unwrap();
+        let mut stmt = db
+            .conn
+            .prepare_cached(
+                "SELECT id, url, title, visited_at, transition, tab_id FROM visits
+                 WHERE (?1 IS NULL OR id < ?1)
+                   AND (?2 IS NULL OR visited_at >= ?2)
+                   AND (?3 IS NULL OR visited_at < ?3)
+                   AND (?4 IS NULL OR url LIKE ?4 ESCAPE '\\' OR title LIKE ?4 ESCAPE '\\')
+                 ORDER BY id DESC LIMIT ?5",
+            )
+            .map_err(db_err)?;
+        let mut visits = stmt
+            .query_map(
+                params![query.before, query.from, query.to, pattern, limit as i64 + 1],
+                visit_from_row,
+            )
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(db_err)?;
+        let next_cursor = if visits.len() > limit {
+            visits.truncate(limit);
+            visits.last().map(|v| v.id)
+        } else {
+            None
+        };
+        Ok(HistoryPage { visits, next_cursor })
+    }
+
+    pub fn last_visit_id(&self) -> Result<i64, String> {
+        let db = self.db.lock().unwrap();
+        db.conn
+            .query_row("SELECT IFNULL(MAX(id), 0) FROM visits", [], |row| row.get(0))
+            .map_err(db_err)
+    }
+
+    // Stats for every URL visited after visit `after`, or for all of them
+    // when it is 0. Recent visits count for more, reloads for nothing, and
+    // typed addresses for twice as much as followed links.
+    pub fn page_stats(&self, after: i64) -> Result<Vec<PageStats>, String> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db
+            .conn
+            .prepare_cached(
+                "SELECT url, title, MAX(id),
+                        SUM(CASE transition
+                                WHEN 'reload' THEN 0.0
+                                WHEN 'typed' THEN 2.0
+                                WHEN 'start_page' THEN 0.5
+                                ELSE 1.0 END
+                            * CASE
+                                WHEN ?1 - visited_at < 4 * ?2 THEN 100
+                                WHEN ?1 - visited_at < 14 * ?2 THEN 70
+                                WHEN ?1 - visited_at < 31 * ?2 THEN 50
+                                WHEN ?1 - visited_at < 90 * ?2 THEN 30
+                                ELSE 10 END)
+                 FROM visits
+                 WHERE ?3 = 0 OR url IN (SELECT url FROM visits WHERE id > ?3)
+                 GROUP BY url",
+            )
+            .map_err(db_err)?;
+        stmt.query_map(params![unix_now_ms() as i64, DAY_MS, after], |row| {
+            Ok(PageStats { url: row.get(0)?, title: row.get(1)?, frecency: row.get(3)? })
+        })
+        .and_then(|rows| rows.collect())
+        .map_err(db_err)
+    }
+
+    // The latest visit to each of the most recently visited URLs.
+    pub fn recent(&self, limit: u32) -> Result<Vec<Visit>, String> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db
+            .conn
+            .prepare_cached(
+                "SELECT MAX(id) AS id, url, title, visited_at, transition, tab_id FROM visits
+                 GROUP BY url ORDER BY id DESC LIMIT ?1",
+            )
+            .map_err(db_err)?;
+        stmt.query_map([limit.min(MAX_PAGE_SIZE)], visit_from_row)
+            .and_then(|rows| rows.collect())
+            .map_err(db_err)
+    }
+
+    pub fn delete_url(&self, url: &str) -> Result<usize, String> {
+        let db = self.db.lock().unwrap();
+        let deleted = db.conn.execute("DELETE FROM visits WHERE url = ?1", [url]).map_err(db_err)?;
+        db.drop_orphaned_text()?;
+        self.changed();
+        Ok(deleted)
+    }
+
+    // Either bound may be left open; with neither, everything goes.
+    pub fn delete_range(&self, from: Option<i64>, to: Option<i64>) -> Result<usize, String> {
+        let db = self.db.lock().unwrap();
+        let deleted = db
+            .conn
+            .execute(
+                "DELETE FROM visits
+                 WHERE (?1 IS NULL OR visited_at >= ?1) AND (?2 IS NULL OR visited_at < ?2)",
+                params![from, to],
+            )
+            .map_err(db_err)?;
+        db.drop_orphaned_text()?;
+        self.changed();
+        Ok(deleted)
+    }
+
+    // Replaces the indexed text of `url`. The row is tied to a visit once the
+    // frontend records one.
+    pub fn index_page(&self, url: &WebUrl, title: Option<&str>, body: &str) -> Result<(), String> {
+        let url = url.without_fragment();
+        let mut db = self.db.lock().unwrap();
+        let tx = db.conn.transaction().map_err(db_err)?;
+        tx.execute("DELETE FROM page_text WHERE url = ?1", [&url]).map_err(db_err)?;
+        tx.execute(
+            "INSERT INTO page_text (url, visit_id, title, body) VALUES (?1, NULL, ?2, ?3)",
+            params![url, title.unwrap_or_default(), body],
+        )
+        .map_err(db_err)?;
+        tx.commit().map_err(db_err)
+    }
+
+    // Best matches first, titles weighing five times as much as body text.
+    pub fn search(&self, query: &str, limit: Option<u32>) -> Result<Vec<PageMatch>, String> {
+        let Some(expr) = match_expression(query) else {
+            return Ok(Vec::new());
+        };
+        let db = self.db.lock().unwrap();
+        let mut stmt = db
+            .conn
+            .prepare_cached(
+                "SELECT page_text.url, page_text.title,
+                        snippet(page_text, 3, char(57344), char(57345), '…', 16),
+                        visits.id, visits.visited_at,
+                        bm25(page_text, 0.0, 0.0, 5.0, 1.0) AS score
+                 FROM page_text LEFT JOIN visits ON visits.id = page_text.visit_id
+                 WHERE page_text MATCH ?1
+                 ORDER BY score LIMIT ?2",
+            )
+            .map_err(db_err)?;
+        let limit = limit.unwrap_or(SEARCH_LIMIT).clamp(1, MAX_PAGE_SIZE);
+        stmt.query_map(params![expr, limit], |row| {
+            Ok(PageMatch {
+                url: row.get(0)?,
+                title: row.get(1)?,
+                snippet: row.get(2)?,
+                visit_id: row.get(3)?,
+                visited_at: row.get(4)?,
+                // bm25 is lower for better matches; flip it so higher reads as better.
+                score: -row.get::<_, f64>(5)?,
+            })
+        })
+        .and_then(|rows| rows.collect())
+        .map_err(db_err)
+    }
+
+    // Zero keeps history forever.
+    pub fn set_retention(&self, days: u32) -> Result<usize, String> {
+        let mut db = self.db.lock().unwrap();
+        db.retention_days = days;
+        let pruned = db.prune(unix_now_ms() as i64)?;
+        if pruned > 0 {
+            self.changed();
+        }
+        Ok(pruned)
+    }
+
+    // Adds the entries as `imported` visits. Runs once: later calls find the
+    // imported visits and do nothing.
+    pub fn migrate_legacy(&self, mut entries: Vec<LegacyEntry>) -> Result<usize, String> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.conn.transaction().map_err(db_err)?;
+        let imported: i64 = tx
+            .query_row("SELECT COUNT(*) FROM visits WHERE transition = 'imported'", [], |row| {
+                row.get(0)
+            })
+            .map_err(db_err)?;
+        if imported > 0 {
+            return Ok(0);
+        }
+        // Entries saved before timestamps were recorded sort as the oldest.
+        let oldest = entries.iter().filter_map(|e| e.timestamp).min();
+        let fallback = oldest.unwrap_or_else(|| unix_now_ms() as i64);
+        entries.sort_by_key(|e| e.timestamp.unwrap_or(fallback));
+        for entry in &entries {
+            tx.execute(
+                "INSERT INTO visits (url, title, visited_at, transition) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    entry.url,
+                    entry.title,
+                    entry.timestamp.unwrap_or(fallback),
+                    Transition::Imported.as_str()
+                ],
+            )
+            .map_err(db_err)?;
+        }
+        tx.commit().map_err(db_err)?;
+        self.changed();
+        Ok(entries.len())
+    }
+}
+
+fn migrate(conn: &Connection) -> Result<(), String> {
+    let version: usize =
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0)).map_err(db_err)?;
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(&format!("BEGIN; {sql} PRAGMA user_version = {}; COMMIT;", i + 1))
+            .map_err(db_err)?;
+    }
+    Ok(())
+}
+
+fn visit_from_row(row: &Row) -> rusqlite::Result<Visit> {
+    Ok(Visit {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        title: row.get(2)?,
+        visited_at: row.get(3)?,
+        transition: Transition::parse(&row.get::<_, String>(4)?),
+        tab_id: row.get(5)?,
+    })
+}
+
+// Turns free text into an FTS5 query matching every word, the last one as a
+// prefix so results appear while it is still being typed.
+fn match_expression(query: &str) -> Option<String> {
+    let words: Vec<&str> =
+        query.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    let (last, rest) = words.split_last()?;
+    let mut terms: Vec<String> = rest.iter().map(|w| format!("\"{w}\"")).collect();
+    terms.push(format!("\"{last}\"*"));
+    Some(terms.join(" "))
+}
+
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+fn db_err(e: rusqlite::Error) -> String {
+    format!("history database: {e}")
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::storage::{set_aside, write_private};
+use crate::to_hex;
+
+const SEED_LEN: usize = 32;
+
+#[derive(Serialize)]
+pub struct IdentityInfo {
+    node_id: String,
+    pubkey: String,
+}
+
+pub struct IdentityStore {
+    path: PathBuf,
+    seed: Mutex<[u8; SEED_LEN]>,
+    // Where an unreadable identity file was moved before a new one replaced it.
+    replaced: Option<PathBuf>,
+}
+
+impl IdentityStore {
+    pub fn load_or_create(path: PathBuf) -> Result<Self, String> {
+        let (seed, replaced) = match fs::read(&path) {
+            Ok(bytes) => match std::str::from_utf8(&bytes).ok().and_then(|t| parse_seed(t.trim())) {
+                Some(seed) => (seed, None),
+                None => {
+                    // A damaged key must not keep the browser from starting.
+                    let aside = set_aside(&path)?;
+                    (create_seed(&path)?, Some(aside))
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (create_seed(&path)?, None),
+            Err(e) => return Err(format!("failed to read identity: {e}")),
+        };
+        Ok(Self { path, seed: Mutex::new(seed), replaced })
+    }
+
+    pub fn replaced(&self) -> Option<&Path> {
+        self.replaced.as_deref()
+    }
+
+    pub fn seed(&self) -> [u8; SEED_LEN] {
+        *self.seed.lock().unwrap()
+    }
+
+    pub fn info(&self) -> Result<IdentityInfo, String> {
+        let keypair = keypair_from_seed(&self.seed())?;
+        Ok(IdentityInfo {
+            node_id: keypair.node_id().to_string(),
+            pubkey: to_hex(&keypair.pubkey),
+        })
+    }
+
+    pub fn regenerate(&self) -> Result<IdentityInfo, String> {
+        let seed = create_seed(&self.path)?;
+        *self.seed.lock().unwrap() = seed;
+        self.info()
+    }
+}
+
+pub fn keypair_from_seed(seed: &[u8; SEED_LEN]) -> Result<nwep::Keypair, String> {
+    nwep::Keypair::from_seed(seed).map_err(|e| format!("{e}"))
+}
+
+fn generate_seed() -> Result<[u8; SEED_LEN], String> {
+    nwep::Keypair::generate()
+        .map(|kp| kp.seed())
+        .map_err(|e| format!("{e}"))
+}
+
+fn parse_seed(hex: &str) -> Option<[u8; SEED_LEN]> {
+    if hex.len() != SEED_LEN * 2 || !hex.is_ascii() {
+        return None;
+    }
+    let mut seed = [0u8; SEED_LEN];
+    for (i, byte) in seed.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(seed)
+}
+
+fn create_seed(path: &Path) -> Result<[u8; SEED_LEN], String> {
+    let seed = generate_seed()?;
+    write_private(path, to_hex(&seed).as_bytes())?;
+    Ok(seed)
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{set_aside, unix_now, write_atomic};
+
+// `last_seen` is only kept to the hour, so a busy node does not rewrite the
+// file on every request.
+const LAST_SEEN_RESOLUTION_SECS: u64 = 60 * 60;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KnownNode {
+    pub pubkey: String,
+    pub first_seen: u64,
+    pub last_seen: u64,
+}
+
+#[derive(Serialize)]
+pub struct KnownNodeEntry {
+    node_id: String,
+    #[serde(flatten)]
+    node: KnownNode,
+}
+
+#[derive(Clone, Serialize)]
+pub struct KeyChange {
+    pub node_id: String,
+    pub pinned_pubkey: String,
+    pub presented_pubkey: String,
+    pub first_seen: u64,
+}
+
+pub enum PinCheck {
+    FirstUse,
+    Matches,
+    Changed,
+}
+
+#[derive(Clone)]
+pub struct KnownNodes {
+    path: Arc<PathBuf>,
+    nodes: Arc<Mutex<BTreeMap<String, KnownNode>>>,
+    // Where an unreadable file was moved, losing every pin it held.
+    replaced: Option<Arc<PathBuf>>,
+}
+
+impl KnownNodes {
+    pub fn load(path: PathBuf) -> Result<Self, String> {
+        let (nodes, replaced) = match fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice(&bytes) {
+                Ok(nodes) => (nodes, None),
+                Err(_) => (BTreeMap::new(), Some(Arc::new(set_aside(&path)?))),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (BTreeMap::new(), None),
+            Err(_) => (BTreeMap::new(), Some(Arc::new(set_aside(&path)?))),
+        };
+        Ok(Self { path: Arc::new(path), nodes: Arc::new(Mutex::new(nodes)), replaced })
+    }
+
+    pub fn replaced(&self) -> Option<&Path> {
+        self.replaced.as_deref().map(PathBuf::as_path)
+    }
+
+    pub fn check(&self, node_id: &str, pubkey: &str) -> Result<PinCheck, String> {
+        let mut nodes = self.nodes.lock().unwrap();
+        let now = unix_now();
+        let check = match nodes.get_mut(node_id) {
+            Some(known) if known.pubkey == pubkey => {
+                if now.saturating_sub(known.last_seen) < LAST_SEEN_RESOLUTION_SECS {
+                    return Ok(PinCheck::Matches);
+                }
+                known.last_seen = now;
+                PinCheck::Matches
+            }
+            Some(_) => return Ok(PinCheck::Changed),
+            None => {
+                nodes.insert(
+                    node_id.to_string(),
+                    KnownNode { pubkey: pubkey.to_string(), first_seen: now, last_seen: now },
+                );
+                PinCheck::FirstUse
+            }
+        };
+        self.save(&nodes)?;
+        Ok(check)
+    }
+
+    pub fn key_change(&self, node_id: &str, presented: &str) -> Option<KeyChange> {
+        let nodes = self.nodes.lock().unwrap();
+        let known = nodes.get(node_id).filter(|known| known.pubkey != presented)?;
+        Some(KeyChange {
+            node_id: node_id.to_string(),
+            pinned_pubkey: known.pubkey.clone(),
+            presented_pubkey: presented.to_string(),
+            first_seen: known.first_seen,
+        })
+    }
+
+    pub fn list(&self) -> Vec<KnownNodeEntry> {
+        self.nodes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, node)| KnownNodeEntry { node_id: id.clone(), node: node.clone() })
+            .collect()
+    }
+
+    pub fn forget(&self, node_id: &str) -> Result<bool, String> {
+        let mut nodes = self.nodes.lock().unwrap();
+        let removed = nodes.remove(node_id).is_some();
+        self.save(&nodes)?;
+        Ok(removed)
+    }
+
+    pub fn trust(&self, node_id: &str, pubkey: &str) -> Result<(), String> {
+        let mut nodes = self.nodes.lock().unwrap();
+        let now = unix_now();
+        nodes.insert(
+            node_id.to_string(),
+            KnownNode { pubkey: pubkey.to_string(), first_seen: now, last_seen: now },
+        );
+        self.save(&nodes)
+    }
+
+    fn save(&self, nodes: &BTreeMap<String, KnownNode>) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(nodes).map_err(|e| e.to_string())?;
+        write_atomic(&self.path, &json)
+    }
+}
@@ -1,131 +1,382 @@
-use serde::Serialize;
+mod bookmarks;
+mod cache;
+mod cancel;
+mod compression;
+mod content;
+mod error;
+mod executor;
+mod fetch;
+mod history;
+mod identity;
+mod known_nodes;
+mod links;
+mod policy;
+mod pool;
+mod progress;
+mod scheme;
+mod settings;
+mod storage;
+mod suggest;
+mod text;
+mod url;
 
-#[derive(Serialize)]
-struct NwepHeader {
-    name: String,
-    value: String,
-}
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 
-fn to_hex(bytes: &[u8]) -> String {
+use bookmarks::{BookmarkNode, BookmarkPatch, BookmarkStore, NewBookmark};
+use cache::{CacheInfo, OfflineMode, ResponseCache};
+use cancel::{FrameLoads, RequestRegistry};
+use executor::{ExecutorStats, FetchExecutor};
+use fetch::{FetchContext, NwepHeader, NwepResult, RequestSpec};
+use history::{
+    HistoryPage, HistoryQuery, HistoryStore, LegacyEntry, NewVisit, PageMatch, Visit,
+};
+use identity::{IdentityInfo, IdentityStore};
+use known_nodes::{KnownNodeEntry, KnownNodes};
+use policy::{NetworkPolicy, NetworkSettings};
+use pool::ConnectionPool;
+use progress::FetchEvent;
+use settings::{Settings, SettingsStore};
+use suggest::{SuggestQuery, Suggester, Suggestions};
+use url::{ParsedUrl, WebUrl};
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
     bytes.iter().map(|b| format!("{:02x}", b)).collect()
 }
 
-#[derive(Serialize)]
-struct LogStep {
-    name: String,
-    ok: bool,
-    detail: Option<String>,
+#[tauri::command]
+async fn nwep_fetch(
+    url: String,
+    request_id: Option<String>,
+    on_progress: Channel<FetchEvent>,
+    app: AppHandle,
+    requests: State<'_, RequestRegistry>,
+    executor: State<'_, FetchExecutor>,
+) -> Result<NwepResult, String> {
+    let registration = requests.register(request_id);
+    let ctx = FetchContext::from_app(&app, registration.token.clone(), Some(on_progress));
+    executor
+        .run(executor::node_key(&url), move || {
+            let _registration = registration;
+            fetch::execute(&ctx, &url, RequestSpec::get())
+        })
+        .await
 }
 
-#[derive(Serialize)]
-struct ConnectionInfo {
-    client_node_id: String,
-    server_node_id: String,
-    server_pubkey: String,
+#[derive(Deserialize)]
+struct NwepRequest {
+    url: String,
+    method: String,
+    #[serde(default)]
+    headers: Vec<NwepHeader>,
+    body: Option<String>,
+    request_id: Option<String>,
 }
 
-#[derive(Serialize)]
-struct NwepResult {
-    ok: bool,
-    error: Option<String>,
-    status: Option<String>,
-    status_details: Option<String>,
-    body: Option<String>,
-    headers: Vec<NwepHeader>,
-    connection: Option<ConnectionInfo>,
-    log: Vec<LogStep>,
+#[tauri::command]
+async fn nwep_request(
+    request: NwepRequest,
+    app: AppHandle,
+    requests: State<'_, RequestRegistry>,
+    executor: State<'_, FetchExecutor>,
+) -> Result<NwepResult, String> {
+    let registration = requests.register(request.request_id);
+    let ctx = FetchContext {
+        cache: None,
+        history: None,
+        ..FetchContext::from_app(&app, registration.token.clone(), None)
+    };
+    let url = request.url;
+    let spec = RequestSpec {
+        method: request.method.to_ascii_uppercase(),
+        headers: request
+            .headers
+            .into_iter()
+            .map(|h| nwep::Header { name: h.name, value: h.value })
+            .collect(),
+        body: request.body.unwrap_or_default().into_bytes(),
+    };
+    executor
+        .run(executor::node_key(&url), move || {
+            let _registration = registration;
+            fetch::execute(&ctx, &url, spec)
+        })
+        .await
+}
+
+#[tauri::command]
+fn nwep_cancel(request_id: String, requests: State<'_, RequestRegistry>) -> bool {
+    requests.cancel(&request_id)
+}
+
+#[tauri::command]
+fn cancel_frame_loads(loads: State<'_, FrameLoads>) {
+    loads.restart();
+}
+
+#[tauri::command]
+fn parse_url(url: String) -> Result<ParsedUrl, String> {
+    WebUrl::parse(&url).map(|u| ParsedUrl::from(&u))
 }
 
-fn extract_path(url: &str) -> String {
-    let without_scheme = url.strip_prefix("web://").unwrap_or(url);
-    if let Some(slash_pos) = without_scheme.find('/') {
-        without_scheme[slash_pos..].to_string()
-    } else {
-        "/".to_string()
+#[tauri::command]
+fn normalize_url(url: String) -> Result<String, String> {
+    WebUrl::parse(&url).map(|u| u.to_string())
+}
+
+#[tauri::command]
+fn resolve_url(base: String, href: String) -> Result<String, String> {
+    let base = WebUrl::parse(&base)?;
+    match base.join(&href) {
+        Some(resolved) => resolved.map(|u| u.to_string()),
+        None => Ok(href),
     }
 }
 
 #[tauri::command]
-async fn nwep_fetch(url: String) -> Result<NwepResult, String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        let mut log: Vec<LogStep> = Vec::new();
+fn get_identity(identity: State<'_, IdentityStore>) -> Result<IdentityInfo, String> {
+    identity.info()
+}
 
-        let keypair = match nwep::Keypair::generate() {
-            Ok(kp) => {
-                log.push(LogStep { name: "generated ephemeral keypair".into(), ok: true, detail: None });
-                kp
-            }
-            Err(e) => {
-                log.push(LogStep { name: "generated ephemeral keypair".into(), ok: false, detail: Some(format!("{e}")) });
-                return Ok(NwepResult {
-                    ok: false, error: Some(format!("{e}")),
-                    status: None, status_details: None, body: None,
-                    headers: vec![], connection: None, log,
-                });
-            }
-        };
+#[tauri::command]
+fn regenerate_identity(
+    identity: State<'_, IdentityStore>,
+    pool: State<'_, ConnectionPool>,
+) -> Result<IdentityInfo, String> {
+    let info = identity.regenerate()?;
+    pool.clear();
+    Ok(info)
+}
 
-        let path = extract_path(&url);
+#[tauri::command]
+fn list_known_nodes(known: State<'_, KnownNodes>) -> Vec<KnownNodeEntry> {
+    known.list()
+}
 
-        let client = match nwep::ClientBuilder::new().connect(keypair, &url) {
-            Ok(c) => {
-                log.push(LogStep { name: "client established connection".into(), ok: true, detail: None });
-                c
-            }
-            Err(e) => {
-                log.push(LogStep { name: "client established connection".into(), ok: false, detail: Some(format!("{e}")) });
-                return Ok(NwepResult {
-                    ok: false, error: Some(format!("{e}")),
-                    status: None, status_details: None, body: None,
-                    headers: vec![], connection: None, log,
-                });
-            }
-        };
-
-        let peer = client.peer_identity();
-        let connection = ConnectionInfo {
-            client_node_id: client.node_id().to_string(),
-            server_node_id: client.peer_node_id().to_string(),
-            server_pubkey: to_hex(&peer.pubkey),
-        };
-
-        let resp = match client.get(&path) {
-            Ok(r) => {
-                let detail = if r.status_details.is_empty() {
-                    r.status.clone()
-                } else {
-                    format!("{} {}", r.status, r.status_details)
-                };
-                log.push(LogStep { name: "fetched resource".into(), ok: true, detail: Some(detail) });
-                r
-            }
-            Err(e) => {
-                log.push(LogStep { name: "fetched resource".into(), ok: false, detail: Some(format!("{e}")) });
-                return Ok(NwepResult {
-                    ok: false, error: Some(format!("{e}")),
-                    status: None, status_details: None, body: None,
-                    headers: vec![], connection: Some(connection), log,
-                });
-            }
-        };
-
-        Ok(NwepResult {
-            ok: true,
-            error: None,
-            status: Some(resp.status),
-            status_details: Some(resp.status_details),
-            body: Some(String::from_utf8_lossy(&resp.body).to_string()),
-            headers: resp
-                .headers
-                .into_iter()
-                .map(|h| NwepHeader { name: h.name, value: h.value })
-                .collect(),
-            connection: Some(connection),
-            log,
-        })
-    })
-    .await
-    .map_err(|e| format!("Task error: {e}"))?
+#[tauri::command]
+fn forget_known_node(
+    node_id: String,
+    known: State<'_, KnownNodes>,
+    pool: State<'_, ConnectionPool>,
+) -> Result<bool, String> {
+    pool.clear();
+    known.forget(&node_id)
+}
+
+#[tauri::command]
+fn trust_known_node(
+    node_id: String,
+    pubkey: String,
+    known: State<'_, KnownNodes>,
+    pool: State<'_, ConnectionPool>,
+) -> Result<(), String> {
+    pool.clear();
+    known.trust(&node_id, &pubkey)
+}
+
+#[tauri::command]
+fn cache_info(cache: State<'_, ResponseCache>) -> CacheInfo {
+    cache.info()
+}
+
+#[tauri::command]
+fn clear_cache(cache: State<'_, ResponseCache>) -> Result<(), String> {
+    cache.clear()
+}
+
+#[tauri::command]
+fn executor_stats(executor: State<'_, FetchExecutor>) -> ExecutorStats {
+    executor.stats()
+}
+
+#[tauri::command]
+fn list_bookmarks(bookmarks: State<'_, BookmarkStore>) -> Vec<BookmarkNode> {
+    bookmarks.list()
+}
+
+#[tauri::command]
+fn migrate_bookmarks(
+    tree: Vec<BookmarkNode>,
+    bookmarks: State<'_, BookmarkStore>,
+) -> Result<Vec<BookmarkNode>, String> {
+    bookmarks.migrate(tree)
+}
+
+#[tauri::command]
+fn add_bookmark(
+    parent_id: Option<String>,
+    node: NewBookmark,
+    bookmarks: State<'_, BookmarkStore>,
+) -> Result<Vec<BookmarkNode>, String> {
+    bookmarks.add(parent_id.as_deref(), node)
+}
+
+#[tauri::command]
+fn update_bookmark(
+    id: String,
+    patch: BookmarkPatch,
+    bookmarks: State<'_, BookmarkStore>,
+) -> Result<Vec<BookmarkNode>, String> {
+    bookmarks.update(&id, patch)
+}
+
+#[tauri::command]
+fn remove_bookmark(
+    id: String,
+    bookmarks: State<'_, BookmarkStore>,
+) -> Result<Vec<BookmarkNode>, String> {
+    bookmarks.remove(&id)
+}
+
+#[tauri::command]
+fn remove_bookmarks_by_url(
+    url: String,
+    bookmarks: State<'_, BookmarkStore>,
+) -> Result<Vec<BookmarkNode>, String> {
+    bookmarks.remove_url(&url)
+}
+
+#[tauri::command]
+fn move_bookmark(
+    id: String,
+    folder_id: Option<String>,
+    index: Option<usize>,
+    bookmarks: State<'_, BookmarkStore>,
+) -> Result<Vec<BookmarkNode>, String> {
+    bookmarks.move_to(&id, folder_id.as_deref(), index)
+}
+
+#[tauri::command]
+fn reorder_bookmarks(
+    parent_id: Option<String>,
+    from_index: usize,
+    to_index: usize,
+    bookmarks: State<'_, BookmarkStore>,
+) -> Result<Vec<BookmarkNode>, String> {
+    bookmarks.reorder(parent_id.as_deref(), from_index, to_index)
+}
+
+#[tauri::command]
+fn import_bookmarks(
+    html: String,
+    bookmarks: State<'_, BookmarkStore>,
+) -> Result<Vec<BookmarkNode>, String> {
+    bookmarks.import_html(&html)
+}
+
+#[tauri::command]
+fn export_bookmarks(bookmarks: State<'_, BookmarkStore>) -> String {
+    bookmarks.export_html()
+}
+
+#[tauri::command]
+fn record_visit(visit: NewVisit, history: State<'_, HistoryStore>) -> Result<i64, String> {
+    history.record(visit)
+}
+
+#[tauri::command]
+fn query_history(
+    query: HistoryQuery,
+    history: State<'_, HistoryStore>,
+) -> Result<HistoryPage, String> {
+    history.query(query)
+}
+
+#[tauri::command]
+fn recent_history(limit: u32, history: State<'_, HistoryStore>) -> Result<Vec<Visit>, String> {
+    history.recent(limit)
+}
+
+#[tauri::command]
+fn delete_history_url(
+    url: String,
+    history: State<'_, HistoryStore>,
+    cache: State<'_, ResponseCache>,
+) -> Result<usize, String> {
+    if let Ok(parsed) = WebUrl::parse(&url) {
+        cache.forget_saved(&parsed.without_fragment())?;
+    }
+    history.delete_url(&url)
+}
+
+#[tauri::command]
+fn delete_history_range(
+    from: Option<i64>,
+    to: Option<i64>,
+    history: State<'_, HistoryStore>,
+    cache: State<'_, ResponseCache>,
+) -> Result<usize, String> {
+    cache.forget_saved_between(from, to)?;
+    history.delete_range(from, to)
+}
+
+#[tauri::command]
+fn search_pages(
+    query: String,
+    limit: Option<u32>,
+    history: State<'_, HistoryStore>,
+) -> Result<Vec<PageMatch>, String> {
+    history.search(&query, limit)
+}
+
+#[tauri::command]
+fn suggest(
+    query: SuggestQuery,
+    suggester: State<'_, Suggester>,
+    history: State<'_, HistoryStore>,
+    bookmarks: State<'_, BookmarkStore>,
+) -> Result<Suggestions, String> {
+    suggester.suggest(&history, &bookmarks, query)
+}
+
+#[tauri::command]
+fn migrate_history(
+    entries: Vec<LegacyEntry>,
+    history: State<'_, HistoryStore>,
+) -> Result<usize, String> {
+    history.migrate_legacy(entries)
+}
+
+#[tauri::command]
+fn get_settings(settings: State<'_, SettingsStore>) -> Settings {
+    settings.get()
+}
+
+#[tauri::command]
+fn set_settings(
+    patch: Map<String, Value>,
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+) -> Result<Settings, String> {
+    let updated = settings.update(patch)?;
+    settings::apply(&app, &updated)?;
+    Ok(updated)
+}
+
+#[tauri::command]
+fn migrate_settings(
+    legacy: Value,
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+) -> Result<Settings, String> {
+    let migrated = settings.migrate(legacy)?;
+    settings::apply(&app, &migrated)?;
+    Ok(migrated)
+}
+
+#[tauri::command]
+fn subscribe_settings(channel: Channel<Settings>, settings: State<'_, SettingsStore>) -> u32 {
+    settings.subscribe(channel)
+}
+
+#[tauri::command]
+fn unsubscribe_settings(id: u32, settings: State<'_, SettingsStore>) {
+    settings.unsubscribe(id);
 }
 
 #[tauri::command]
@@ -133,6 +384,15 @@ fn get_app_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
 }
 
+// Tells the user a damaged file was moved aside and what starting over means.
+fn warn_replaced(app: &tauri::App, title: &str, message: &str, aside: &Path) {
+    app.dialog()
+        .message(format!("{message}\n\nThe unreadable file was kept at {}.", aside.display()))
+        .title(title)
+        .kind(MessageDialogKind::Warning)
+        .show(|_| {});
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     nwep::init().expect("failed to initialize nwep");
@@ -148,7 +408,131 @@ pub fn run() {
         .plugin(tauri_plugin_process::init());
 
     builder
-        .invoke_handler(tauri::generate_handler![nwep_fetch, get_app_version])
+        .register_asynchronous_uri_scheme_protocol(scheme::SCHEME, scheme::handle)
+        .setup(|app| {
+            let dir = app.path().app_data_dir()?;
+            app.manage(SettingsStore::load(dir.join("settings.json")));
+            let identity = IdentityStore::load_or_create(dir.join("identity.key"))?;
+            if let Some(aside) = identity.replaced() {
+                warn_replaced(
+                    app,
+                    "Client identity replaced",
+                    "The saved client identity could not be read, so a new one was created. \
+                     Nodes that knew the old identity will see this browser as a new client.",
+                    aside,
+                );
+            }
+            app.manage(identity);
+            let known = KnownNodes::load(dir.join("known_nodes.json"))?;
+            if let Some(aside) = known.replaced() {
+                warn_replaced(
+                    app,
+                    "Known node keys lost",
+                    "The keys pinned for known nodes could not be read. Each node's key will be \
+                     pinned again on the next visit, so a key change since then cannot be \
+                     detected.",
+                    aside,
+                );
+            }
+            app.manage(known);
+            let bookmarks = BookmarkStore::load(dir.join("bookmarks.json"))?;
+            if let Some(aside) = bookmarks.replaced() {
+                warn_replaced(
+                    app,
+                    "Bookmarks could not be read",
+                    "The saved bookmarks could not be read, so the browser started with none. \
+                     Bookmarks exported earlier can be imported again from Settings.",
+                    aside,
+                );
+            }
+            app.manage(bookmarks);
+            let history = match HistoryStore::open_or_replace(&dir.join("history.sqlite")) {
+                Ok((history, aside)) => {
+                    if let Some(aside) = aside {
+                        warn_replaced(
+                            app,
+                            "History could not be read",
+                            "The browsing history database could not be opened, so a new, \
+                             empty one was started.",
+                            &aside,
+                        );
+                    }
+                    history
+                }
+                Err(e) => {
+                    app.dialog()
+                        .message(format!(
+                            "The browsing history database could not be opened: {e}\n\n\
+                             Pages visited now are remembered only until the browser closes."
+                        ))
+                        .title("History unavailable")
+                        .kind(MessageDialogKind::Warning)
+                        .show(|_| {});
+                    HistoryStore::in_memory()?
+                }
+            };
+            app.manage(history);
+            app.manage(Suggester::default());
+            app.manage(ResponseCache::load(
+                app.path().app_cache_dir()?.join("responses"),
+                dir.join("saved_pages"),
+            ));
+            app.manage(OfflineMode::default());
+            app.manage(NetworkSettings::default());
+            app.manage(FetchExecutor::new(NetworkPolicy::default().max_concurrent));
+
+            let pool = ConnectionPool::default();
+            app.manage(pool.clone());
+            app.manage(RequestRegistry::default());
+            app.manage(FrameLoads::default());
+            std::thread::spawn(move || loop {
+                std::thread::sleep(pool::SWEEP_INTERVAL);
+                pool.sweep();
+            });
+            settings::apply(app.handle(), &app.state::<SettingsStore>().get())?;
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            nwep_fetch,
+            nwep_request,
+            nwep_cancel,
+            cancel_frame_loads,
+            parse_url,
+            normalize_url,
+            resolve_url,
+            get_identity,
+            regenerate_identity,
+            list_known_nodes,
+            forget_known_node,
+            trust_known_node,
+            cache_info,
+            clear_cache,
+            executor_stats,
+            list_bookmarks,
+            migrate_bookmarks,
+            add_bookmark,
+            update_bookmark,
+            remove_bookmark,
+            remove_bookmarks_by_url,
+            move_bookmark,
+            reorder_bookmarks,
+            import_bookmarks,
+            export_bookmarks,
+            record_visit,
+            query_history,
+            recent_history,
+            delete_history_url,
+            delete_history_range,
+            migrate_history,
+            search_pages,
+            suggest,
+            get_settings,
+            set_settings,
+            migrate_settings,
+            subscribe_settings,
+            unsubscribe_settings,
+            get_app_version
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
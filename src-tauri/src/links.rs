@@ -0,0 +1,259 @@
+use crate::text::{decode_entities, find_ignore_case};
+use crate::url::WebUrl;
+
+// Links and forms become tab navigations, so they keep the canonical address.
+const LINK_ATTRIBUTES: &[(&str, &str)] = &[("a", "href"), ("area", "href"), ("form", "action")];
+// Subresources are loaded by the webview itself, through the scheme handler.
+const RESOURCE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("img", "src"),
+    ("script", "src"),
+    ("link", "href"),
+    ("iframe", "src"),
+    ("source", "src"),
+    ("video", "src"),
+    ("audio", "src"),
+    ("embed", "src"),
+];
+
+// Pages are rendered from a string with no base URL, so every link and form
+// target is made absolute against the page's own `web://` URL before serving.
+// Subresources are pointed at the scheme handler, and a `<base>` sends
+// anything resolved later by scripts and styles the same way.
+pub fn rewrite_links(html: &str, base: &WebUrl) -> String {
+    let own_base = base_href(html);
+    let base = match own_base.as_ref().and_then(|href| base.join(href)) {
+        Some(Ok(url)) => url,
+        _ => base.clone(),
+    };
+    let frame_base = base.frame_url();
+    let mut base_tag =
+        own_base.is_none().then(|| format!("<base href=\"{}\">", escape_attribute(&frame_base)));
+
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if !rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!') {
+            out.push('<');
+            rest = &rest[1..];
+            continue;
+        }
+        let raw = skip_raw(rest);
+        if rest.starts_with("<!--") {
+            let skipped = raw.unwrap_or(rest.len());
+            out.push_str(&rest[..skipped]);
+            rest = &rest[skipped..];
+            continue;
+        }
+
+        let end = tag_end(rest);
+        let tag = &rest[..end];
+        let name = tag_name(tag);
+        // Ahead of the first element that is not `<html>`, or right inside `<head>`.
+        let opens_head = name.eq_ignore_ascii_case("head");
+        if !opens_head && !name.is_empty() && !name.eq_ignore_ascii_case("html") {
+            out.push_str(&base_tag.take().unwrap_or_default());
+        }
+        let rewritten = if name.eq_ignore_ascii_case("base") {
+            attribute_value(tag, "href").map(|(range, _)| replace_value(tag, range, &frame_base))
+        } else if let Some(attribute) = attribute_for(LINK_ATTRIBUTES, name) {
+            rewrite_attribute(tag, attribute, &base, WebUrl::to_string)
+        } else if let Some(attribute) = attribute_for(RESOURCE_ATTRIBUTES, name) {
+            rewrite_attribute(tag, attribute, &base, WebUrl::frame_url)
+        } else {
+            None
+        };
+        out.push_str(rewritten.as_deref().unwrap_or(tag));
+        if opens_head {
+            out.push_str(&base_tag.take().unwrap_or_default());
+        }
+        // The bodies of <script> and <style> are copied through untouched.
+        let next = raw.unwrap_or(end);
+        out.push_str(&rest[end..next]);
+        rest = &rest[next..];
+    }
+    out.push_str(rest);
+    out.push_str(&base_tag.unwrap_or_default());
+    out
+}
+
+fn attribute_for(attributes: &[(&str, &'static str)], tag_name: &str) -> Option<&'static str> {
+    attributes.iter().find(|(name, _)| tag_name.eq_ignore_ascii_case(name)).map(|(_, a)| *a)
+}
+
+// Comments and the bodies of <script>/<style> are copied through untouched.
+pub fn skip_raw(input: &str) -> Option<usize> {
+    if input.starts_with("<!--") {
+        return Some(input.find("-->").map_or(input.len(), |i| i + 3));
+    }
+    let name = tag_name(input).to_ascii_lowercase();
+    if name != "script" && name != "style" {
+        return None;
+    }
+    let close = format!("</{name}");
+    let body_start = tag_end(input);
+    let body_end =
+        find_ignore_case(&input[body_start..], &close).map_or(input.len(), |i| body_start + i);
+    Some(body_end)
+}
+
+pub fn tag_name(tag: &str) -> &str {
+    let name = tag.strip_prefix('<').unwrap_or(tag);
+    let len = name
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(name.len());
+    &name[..len]
+}
+
+// Finds the `>` that closes the tag, skipping over quoted attribute values.
+pub fn tag_end(input: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in input.char_indices().skip(1) {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+    input.len()
+}
+
+fn base_href(html: &str) -> Option<String> {
+    let start = find_ignore_case(html, "<base")?;
+    let tag = &html[start..start + tag_end(&html[start..])];
+    attribute_value(tag, "href").map(|(_, value)| decode_entities(value))
+}
+
+// Returns the byte range of the attribute's value within `tag`.
+fn attribute_value<'a>(tag: &'a str, name: &str) -> Option<(std::ops::Range<usize>, &'a str)> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(found) = lower[from..].find(name) {
+        let at = from + found;
+        from = at + name.len();
+        let preceded_by_space = lower[..at].ends_with(|c: char| c.is_ascii_whitespace());
+        let after = lower[from..].trim_start();
+        if !preceded_by_space || !after.starts_with('=') {
+            continue;
+        }
+        let value_start = tag.len() - after[1..].trim_start().len();
+        let value = &tag[value_start..];
+        let range = match value.chars().next()? {
+            q @ ('"' | '\'') => {
+                let len = value[1..].find(q)?;
+                value_start + 1..value_start + 1 + len
+            }
+            _ => {
+                let len = value
+                    .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                    .unwrap_or(value.len());
+                value_start..value_start + len
+            }
+        };
+        return Some((range.clone(), &tag[range]));
+    }
+    None
+}
+
+fn rewrite_attribute(
+    tag: &str,
+    name: &str,
+    base: &WebUrl,
+    address: fn(&WebUrl) -> String,
+) -> Option<String> {
+    let (range, value) = attribute_value(tag, name)?;
+    let reference = decode_entities(value);
+    if reference.starts_with('#') {
+        return None;
+    }
+    let resolved = address(&base.join(&reference)?.ok()?);
+    Some(replace_value(tag, range, &resolved))
+}
+
+fn replace_value(tag: &str, range: std::ops::Range<usize>, value: &str) -> String {
+    let value = escape_attribute(value);
+    let quoted = tag[..range.start].ends_with(['"', '\'']);
+    let value = if quoted { value } else { format!("\"{value}\"") };
+    format!("{}{value}{}", &tag[..range.start], &tag[range.end..])
+}
+
+fn escape_attribute(value: &str) -> String {
+    value.replace('&', "&amp;").replace('"', "&quot;").replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(html: &str) -> String {
+        rewrite_links(html, &WebUrl::parse("web://node1:7000/dir/page.html").unwrap())
+    }
+
+    fn frame(path: &str) -> String {
+        WebUrl::parse(&format!("web://node1:7000{path}")).unwrap().frame_url()
+    }
+
+    #[test]
+    fn links_become_canonical_addresses() {
+        assert_eq!(
+            rewrite("<head></head><a href=\"next.html\">n</a><area href='/map'><form action=?q>"),
+            format!(
+                "<head><base href=\"{}\"></head>\
+                 <a href=\"web://[node1]:7000/dir/next.html\">n</a>\
+                 <area href='web://[node1]:7000/map'>\
+                 <form action=\"web://[node1]:7000/dir/page.html?q\">",
+                frame("/dir/page.html"),
+            ),
+        );
+    }
+
+    #[test]
+    fn subresources_go_through_the_scheme_handler() {
+        let html = rewrite(
+            "<head><link rel=stylesheet href=\"css/site.css\"><script src=\"/app.js\"></script>\
+             </head><img src=a.png><iframe src=\"//node2/embed\"></iframe>",
+        );
+        assert!(html.contains(&format!("href=\"{}\"", frame("/dir/css/site.css"))));
+        assert!(html.contains(&format!("src=\"{}\"", frame("/app.js"))));
+        assert!(html.contains(&format!("src=\"{}\"", frame("/dir/a.png"))));
+        let node2 = WebUrl::parse("web://node2/embed").unwrap().frame_url();
+        assert!(html.contains(&format!("src=\"{node2}\"")));
+    }
+
+    #[test]
+    fn leaves_fragments_other_schemes_and_raw_text_alone() {
+        let html = "<head></head><a href=\"#top\">t</a><a href=\"https://example.com/\">e</a>\
+                    <!-- <a href=\"x\"> --><script>var s = '<a href=\"y\">';</script>\
+                    <style>a[href=\"z\"] {}</style>";
+        let rewritten = rewrite(html);
+        let base = format!("<base href=\"{}\">", frame("/dir/page.html"));
+        assert_eq!(rewritten, html.replacen("<head>", &format!("<head>{base}"), 1));
+    }
+
+    #[test]
+    fn decodes_and_escapes_attribute_values() {
+        assert!(rewrite("<a href=\"a?x=1&amp;y='2'\">")
+            .contains("href=\"web://[node1]:7000/dir/a?x=1&amp;y=&#39;2&#39;\""));
+    }
+
+    #[test]
+    fn resolves_against_the_page_base() {
+        let html = rewrite("<html><head><base href=\"sub/\"></head><a href=\"x\">x</a>");
+        assert!(html.contains(&format!("<base href=\"{}\">", frame("/dir/sub/"))));
+        assert!(html.contains("href=\"web://[node1]:7000/dir/sub/x\""));
+        assert_eq!(html.matches("<base").count(), 1);
+    }
+
+    #[test]
+    fn places_a_base_without_a_head() {
+        let base = format!("<base href=\"{}\">", frame("/dir/page.html"));
+        assert_eq!(
+            rewrite("<!DOCTYPE html><html><p>hi</p>"),
+            format!("<!DOCTYPE html><html>{base}<p>hi</p>"),
+        );
+        assert_eq!(rewrite("plain text"), format!("plain text{base}"));
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancelToken;
+use crate::executor::{self, Slot, MAX_WORKERS};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const MAX_RETRIES: u32 = 10;
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkPolicy {
+    pub connect_timeout_ms: u64,
+    pub request_timeout_ms: u64,
+    pub retries: u32,
+    pub backoff_ms: u64,
+    pub max_concurrent: usize,
+}
+
+impl Default for NetworkPolicy {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 10_000,
+            request_timeout_ms: 30_000,
+            retries: 2,
+            backoff_ms: 500,
+            max_concurrent: 6,
+        }
+    }
+}
+
+impl NetworkPolicy {
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.connect_timeout_ms.max(1))
+    }
+
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.request_timeout_ms.max(1))
+    }
+
+    // Exponential: the first retry waits `backoff_ms`, each later one twice as long.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u64 << retry.saturating_sub(1).min(16);
+        Duration::from_millis(self.backoff_ms.saturating_mul(factor)).min(MAX_BACKOFF)
+    }
+
+    pub fn clamped(self) -> Self {
+        Self {
+            retries: self.retries.min(MAX_RETRIES),
+            max_concurrent: self.max_concurrent.clamp(1, MAX_WORKERS),
+            ..self
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct NetworkSettings(Mutex<NetworkPolicy>);
+
+impl NetworkSettings {
+    pub fn get(&self) -> NetworkPolicy {
+        *self.0.lock().unwrap()
+    }
+
+    pub fn set(&self, policy: NetworkPolicy) {
+        *self.0.lock().unwrap() = policy.clamped();
+    }
+}
+
+pub enum Wait<T> {
+    Done(T),
+    TimedOut,
+    Cancelled,
+}
+
+// nwep calls block without a deadline, so they run on their own thread and
+// are abandoned if they overrun. A late result is simply dropped, but the
+// thread holds on to the executor slot until the call actually returns.
+pub fn wait_for<T, F>(timeout: Duration, cancel: &CancelToken, f: F) -> Wait<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let call = executor::current_slot().map(Slot::begin_call);
+    std::thread::spawn(move || {
+        let _call = call;
+        let _ = tx.send(f());
+    });
+    let deadline = Instant::now() + timeout;
+    loop {
+        if cancel.is_cancelled() {
+            return Wait::Cancelled;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Wait::TimedOut;
+        }
+        match rx.recv_timeout(remaining.min(POLL_INTERVAL)) {
+            Ok(value) => return Wait::Done(value),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Wait::TimedOut,
+        }
+    }
+}
+
+// Waits for calls `wait_for` gave up on to return, so that a retry does not
+// run alongside them under the same executor slot.
+pub fn settle(timeout: Duration, cancel: &CancelToken) -> Wait<()> {
+    let Some(slot) = executor::current_slot() else {
+        return Wait::Done(());
+    };
+    let deadline = Instant::now() + timeout;
+    while slot.calls() > 0 {
+        if cancel.is_cancelled() {
+            return Wait::Cancelled;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Wait::TimedOut;
+        }
+        std::thread::sleep(remaining.min(POLL_INTERVAL));
+    }
+    Wait::Done(())
+}
+
+// Returns false if the wait was cut short by cancellation.
+pub fn sleep(duration: Duration, cancel: &CancelToken) -> bool {
+    let deadline = Instant::now() + duration;
+    while !cancel.is_cancelled() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return true;
+        }
+        std::thread::sleep(remaining.min(POLL_INTERVAL));
+    }
+    false
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+// A pooled client is handed to several requests at once, each on its own
+// thread, so the nwep client itself has to be shareable between threads.
+const _: () = {
+    const fn shareable<T: Send + Sync>() {}
+    shareable::<nwep::Client>()
+};
+
+struct PooledClient {
+    client: Arc<nwep::Client>,
+    last_used: Instant,
+}
+
+#[derive(Clone, Default)]
+pub struct ConnectionPool {
+    clients: Arc<Mutex<HashMap<String, PooledClient>>>,
+}
+
+impl ConnectionPool {
+    pub fn get(&self, key: &str) -> Option<Arc<nwep::Client>> {
+        let mut clients = self.clients.lock().unwrap();
+        let now = Instant::now();
+        clients.retain(|_, c| now.duration_since(c.last_used) < IDLE_TIMEOUT);
+        clients.get_mut(key).map(|c| {
+            c.last_used = now;
+            c.client.clone()
+        })
+    }
+
+    pub fn insert(&self, key: String, client: Arc<nwep::Client>) {
+        self.clients.lock().unwrap().insert(
+            key,
+            PooledClient { client, last_used: Instant::now() },
+        );
+    }
+
+    pub fn remove(&self, key: &str) {
+        self.clients.lock().unwrap().remove(key);
+    }
+
+    pub fn clear(&self) {
+        self.clients.lock().unwrap().clear();
+    }
+
+    pub fn sweep(&self) {
+        let now = Instant::now();
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|_, c| now.duration_since(c.last_used) < IDLE_TIMEOUT);
+    }
+}
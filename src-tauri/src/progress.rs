@@ -0,0 +1,74 @@
+use std::time::Instant;
+
+use serde::Serialize;
+use tauri::ipc::Channel;
+
+use crate::fetch::LogStep;
+
+// nwep hands over a response only once its body is complete, so there is no
+// byte-level progress to report between `HeadersReceived` and the last step.
+#[derive(Clone, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum FetchEvent {
+    Step(LogStep),
+    Connecting { url: String },
+    Connected { reused: bool },
+    HeadersReceived { status: String, header_count: usize },
+}
+
+pub struct StepLog {
+    steps: Vec<LogStep>,
+    channel: Option<Channel<FetchEvent>>,
+    started: Instant,
+    mark: Instant,
+    first_byte_ms: Option<f64>,
+}
+
+impl Default for StepLog {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl StepLog {
+    pub fn new(channel: Option<Channel<FetchEvent>>) -> Self {
+        let now = Instant::now();
+        Self { steps: Vec::new(), channel, started: now, mark: now, first_byte_ms: None }
+    }
+
+    // Steps run back to back, so each one is timed from the end of the previous.
+    pub fn push(&mut self, mut step: LogStep) {
+        let now = Instant::now();
+        step.offset_ms = ms(self.mark - self.started);
+        step.duration_ms = ms(now - self.mark);
+        self.mark = now;
+        self.emit(FetchEvent::Step(step.clone()));
+        self.steps.push(step);
+    }
+
+    pub fn mark_first_byte(&mut self) {
+        self.first_byte_ms.get_or_insert(ms(self.started.elapsed()));
+    }
+
+    pub fn first_byte_ms(&self) -> Option<f64> {
+        self.first_byte_ms
+    }
+
+    pub fn elapsed_ms(&self) -> f64 {
+        ms(self.started.elapsed())
+    }
+
+    pub fn emit(&self, event: FetchEvent) {
+        if let Some(channel) = &self.channel {
+            let _ = channel.send(event);
+        }
+    }
+
+    pub fn into_steps(self) -> Vec<LogStep> {
+        self.steps
+    }
+}
+
+fn ms(d: std::time::Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
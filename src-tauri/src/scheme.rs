@@ -0,0 +1,129 @@
+use tauri::http::{header, Request, Response, StatusCode, Uri};
+use tauri::{Manager, Runtime, UriSchemeContext, UriSchemeResponder};
+
+use crate::cancel::FrameLoads;
+use crate::content;
+use crate::executor::FetchExecutor;
+use crate::fetch::{self, FetchContext, RequestSpec};
+use crate::progress::StepLog;
+use crate::url::WebUrl;
+
+pub const SCHEME: &str = "web";
+
+pub fn handle<R: Runtime>(
+    ctx: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+    let url = match WebUrl::parse(&request_url(&request)) {
+        Ok(url) => url,
+        Err(e) => {
+            responder.respond(error_response(StatusCode::BAD_REQUEST, e));
+            return;
+        }
+    };
+    let executor = app.state::<FetchExecutor>().inner().clone();
+    let cancel = app.state::<FrameLoads>().token();
+    executor.submit(url.node_id().to_string(), move || {
+        // Subresources are not pages of their own, so their text stays out of history search.
+        let ctx = FetchContext { history: None, ..FetchContext::from_app(&app, cancel, None) };
+        let mut log = StepLog::default();
+        let result = fetch::exchange(&ctx, &url, &mut log, RequestSpec::get());
+        let response = match result {
+            Ok(fetch::Exchange { response: resp, url, .. }) => {
+                let mut content_type =
+                    content::detect_content_type(&resp.headers, &url.to_string(), &resp.body);
+                // A guessed text type says nothing of the charset, so UTF-8 is named
+                // when the body is valid UTF-8; otherwise the page's own meta decides.
+                if content::header(&resp.headers, "content-type").is_none()
+                    && content::is_text(&content_type)
+                    && std::str::from_utf8(&resp.body).is_ok()
+                {
+                    content_type.push_str("; charset=utf-8");
+                }
+                Response::builder()
+                    .status(status_code(&resp.status))
+                    .header(header::CONTENT_TYPE, content_type)
+                    .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                    .body(resp.body)
+                    .unwrap_or_else(|_| internal_error())
+            }
+            Err((e, _)) => error_response(StatusCode::BAD_GATEWAY, e.to_string()),
+        };
+        responder.respond(response);
+    });
+}
+
+fn error_response(status: StatusCode, message: String) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(message.into_bytes())
+        .unwrap_or_else(|_| internal_error())
+}
+
+fn internal_error() -> Response<Vec<u8>> {
+    Response::builder()
+        .status(StatusCode::INTERNAL_SERVER_ERROR)
+        .body(Vec::new())
+        .unwrap()
+}
+
+// Windows and Android expose custom schemes as `http://web.localhost/...`,
+// so there the nwep authority travels as the first path segment instead
+// (see `WebUrl::frame_url`). A root-relative reference resolved by the
+// webview loses that segment; it is taken from the referring resource.
+fn request_url<B>(request: &Request<B>) -> String {
+    let uri = request.uri();
+    let path_and_query = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    match uri.host() {
+        Some(host) if host.ends_with(".localhost") => {
+            let inherited = match path_authority(path_and_query) {
+                Some(_) => None,
+                None => referer_authority(request),
+            };
+            match inherited {
+                Some(authority) => format!("{SCHEME}://{authority}{path_and_query}"),
+                None => format!("{SCHEME}:/{path_and_query}"),
+            }
+        }
+        _ => {
+            let authority = uri.authority().map(|a| a.as_str()).unwrap_or_default();
+            format!("{SCHEME}://{authority}{path_and_query}")
+        }
+    }
+}
+
+// Frame URLs always spell out the port, which tells the authority segment
+// apart from an ordinary first path segment.
+fn path_authority(path: &str) -> Option<&str> {
+    let segment = path.strip_prefix('/')?.split(['/', '?', '#']).next()?;
+    segment.contains(':').then_some(segment)
+}
+
+fn referer_authority<B>(request: &Request<B>) -> Option<String> {
+    let referer: Uri = request.headers().get(header::REFERER)?.to_str().ok()?.parse().ok()?;
+    if !referer.host()?.ends_with(".localhost") {
+        return None;
+    }
+    path_authority(referer.path()).map(str::to_string)
+}
+
+fn status_code(status: &str) -> StatusCode {
+    if let Ok(code) = status.parse::<u16>() {
+        return StatusCode::from_u16(code).unwrap_or(StatusCode::BAD_GATEWAY);
+    }
+    match status.to_ascii_lowercase().as_str() {
+        "ok" | "success" => StatusCode::OK,
+        "created" => StatusCode::CREATED,
+        "no_content" => StatusCode::NO_CONTENT,
+        "not_modified" => StatusCode::NOT_MODIFIED,
+        "bad_request" => StatusCode::BAD_REQUEST,
+        "unauthorized" => StatusCode::UNAUTHORIZED,
+        "forbidden" => StatusCode::FORBIDDEN,
+        "not_found" => StatusCode::NOT_FOUND,
+        _ => StatusCode::BAD_GATEWAY,
+    }
+}
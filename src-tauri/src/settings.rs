@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::cache::OfflineMode;
+use crate::executor::FetchExecutor;
+use crate::history::HistoryStore;
+use crate::policy::{NetworkPolicy, NetworkSettings};
+use crate::storage::write_atomic;
+
+const VERSION: u64 = 1;
+
+// Each entry upgrades stored settings from the version at its index to the
+// next. Version 0 is the bare object the frontend kept in localStorage under
+// `nwep-settings-v1`; from version 1 on the file is `{ version, settings }`.
+const MIGRATIONS: &[fn(Value) -> Value] = &[|legacy| json!({ "version": 1, "settings": legacy })];
+
+// The ranges the zoom controls in the UI stay within.
+const MIN_ZOOM: f64 = 0.25;
+const MAX_ZOOM: f64 = 3.0;
+const MIN_UI_SCALE: f64 = 0.5;
+const MAX_UI_SCALE: f64 = 2.0;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NewTabAction {
+    Empty,
+    Homepage,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForceUi {
+    Auto,
+    Mobile,
+    Desktop,
+}
+
+// Field names follow the frontend's `BrowserSettings`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    homepage: String,
+    new_tab_action: NewTabAction,
+    default_zoom: f64,
+    ui_scale: f64,
+    search_bookmarks: bool,
+    search_history: bool,
+    history_enabled: bool,
+    history_retention_days: u32,
+    offline_mode: bool,
+    connect_timeout_ms: u64,
+    request_timeout_ms: u64,
+    retry_count: u32,
+    retry_backoff_ms: u64,
+    max_concurrent_requests: usize,
+    developer_force_mobile_ui: ForceUi,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let network = NetworkPolicy::default();
+        Self {
+            homepage: String::new(),
+            new_tab_action: NewTabAction::Empty,
+            default_zoom: 1.0,
+            ui_scale: 1.0,
+            search_bookmarks: true,
+            search_history: true,
+            history_enabled: true,
+            history_retention_days: 365,
+            offline_mode: false,
+            connect_timeout_ms: network.connect_timeout_ms,
+            request_timeout_ms: network.request_timeout_ms,
+            retry_count: network.retries,
+            retry_backoff_ms: network.backoff_ms,
+            max_concurrent_requests: network.max_concurrent,
+            developer_force_mobile_ui: ForceUi::Auto,
+        }
+    }
+}
+
+impl Settings {
+    fn network_policy(&self) -> NetworkPolicy {
+        NetworkPolicy {
+            connect_timeout_ms: self.connect_timeout_ms,
+            request_timeout_ms: self.request_timeout_ms,
+            retries: self.retry_count,
+            backoff_ms: self.retry_backoff_ms,
+            max_concurrent: self.max_concurrent_requests,
+        }
+    }
+
+    // Brings values the UI would never produce back into range.
+    fn normalized(mut self) -> Self {
+        self.default_zoom = self.default_zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.ui_scale = self.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+        let network = self.network_policy().clamped();
+        self.retry_count = network.retries;
+        self.max_concurrent_requests = network.max_concurrent;
+        self
+    }
+
+    // Keeps every stored value that still fits its field; the rest fall back
+    // to their defaults rather than losing the whole file.
+    fn from_stored(stored: &Map<String, Value>) -> Self {
+        let Ok(Value::Object(mut merged)) = serde_json::to_value(Self::default()) else {
+            return Self::default();
+        };
+        for (key, value) in stored {
+            let Some(previous) = merged.insert(key.clone(), value.clone()) else {
+                merged.remove(key);
+                continue;
+            };
+            if serde_json::from_value::<Self>(Value::Object(merged.clone())).is_err() {
+                merged.insert(key.clone(), previous);
+            }
+        }
+        serde_json::from_value::<Self>(Value::Object(merged)).unwrap_or_default().normalized()
+    }
+}
+
+struct State {
+    settings: Settings,
+    // Until the file exists, the settings the frontend kept may be adopted.
+    stored: bool,
+}
+
+#[derive(Default)]
+struct Subscribers {
+    next_id: u32,
+    channels: HashMap<u32, Channel<Settings>>,
+}
+
+// The settings file, owned by the backend. Every change is written through
+// and then sent to each subscriber.
+pub struct SettingsStore {
+    path: PathBuf,
+    state: Mutex<State>,
+    subscribers: Mutex<Subscribers>,
+}
+
+impl SettingsStore {
+    pub fn load(path: PathBuf) -> Self {
+        let stored: Option<Value> =
+            fs::read(&path).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok());
+        let state =
+            State { stored: stored.is_some(), settings: stored.map(upgrade).unwrap_or_default() };
+        Self { path, state: Mutex::new(state), subscribers: Mutex::default() }
+    }
+
+    pub fn get(&self) -> Settings {
+        self.state.lock().unwrap().settings.clone()
+    }
+
+    // Only takes effect until the settings file has first been written.
+    pub fn migrate(&self, legacy: Value) -> Result<Settings, String> {
+        let mut state = self.state.lock().unwrap();
+        if !state.stored {
+            self.write(&mut state, upgrade(legacy))?;
+        }
+        Ok(state.settings.clone())
+    }
+
+    // Changes the fields present in `patch`. An unknown field or a value of
+    // the wrong type rejects the whole patch.
+    pub fn update(&self, patch: Map<String, Value>) -> Result<Settings, String> {
+        let mut state = self.state.lock().unwrap();
+        let Ok(Value::Object(mut merged)) = serde_json::to_value(&state.settings) else {
+            return Err("failed to encode settings".into());
+        };
+        for (key, value) in patch {
+            if !merged.contains_key(&key) {
+                return Err(format!("unknown setting `{key}`"));
+            }
+            merged.insert(key, value);
+        }
+        let settings: Settings = serde_json::from_value(Value::Object(merged))
+            .map_err(|e| format!("invalid settings: {e}"))?;
+        self.write(&mut state, settings.normalized())?;
+        Ok(state.settings.clone())
+    }
+
+    pub fn subscribe(&self, channel: Channel<Settings>) -> u32 {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.next_id += 1;
+        let id = subscribers.next_id;
+        subscribers.channels.insert(id, channel);
+        id
+    }
+
+    pub fn unsubscribe(&self, id: u32) {
+        self.subscribers.lock().unwrap().channels.remove(&id);
+    }
+
+    fn write(&self, state: &mut State, settings: Settings) -> Result<(), String> {
+        let file = json!({ "version": VERSION, "settings": settings });
+        let bytes = serde_json::to_vec_pretty(&file).map_err(|e| e.to_string())?;
+        write_atomic(&self.path, &bytes)?;
+        state.settings = settings;
+        state.stored = true;
+        // A subscriber whose webview has gone away stops receiving changes.
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.channels.retain(|_, channel| channel.send(state.settings.clone()).is_ok());
+        Ok(())
+    }
+}
+
+// Pushes the settings the backend acts on into the state that uses them.
+pub fn apply<R: Runtime>(app: &AppHandle<R>, settings: &Settings) -> Result<(), String> {
+    app.state::<OfflineMode>().set(settings.offline_mode);
+    let network = app.state::<NetworkSettings>();
+    network.set(settings.network_policy());
+    app.state::<FetchExecutor>().set_limit(network.get().max_concurrent);
+    let history = app.state::<HistoryStore>();
+    history.set_enabled(settings.history_enabled);
+    history.set_retention(settings.history_retention_days).map(|_| ())
+}
+
+fn upgrade(stored: Value) -> Settings {
+    let version = stored.get("version").and_then(Value::as_u64).unwrap_or(0) as usize;
+    let upgraded = MIGRATIONS.iter().skip(version).fold(stored, |value, migrate| migrate(value));
+    match upgraded.get("settings") {
+        Some(Value::Object(settings)) => Settings::from_stored(settings),
+        _ => Settings::default(),
+    }
+}
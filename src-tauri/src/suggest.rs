@@ -0,0 +1,560 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bookmarks::BookmarkStore;
+use crate::history::{HistoryStore, PageStats};
+use crate::scheme::SCHEME;
+use crate::storage::unix_now_ms;
+use crate::url::WebUrl;
+
+const DEFAULT_LIMIT: usize = 8;
+const MAX_LIMIT: usize = 50;
+// Frecency drifts as visits age into older buckets, so the index is rebuilt
+// at least this often.
+const REBUILD_INTERVAL_MS: u64 = 24 * 60 * 60 * 1000;
+// A bookmark weighs as much as one recent visit.
+const BOOKMARK_FRECENCY: f64 = 100.0;
+// Words are only compared up to this length when looking for typos.
+const MAX_FUZZY_CHARS: usize = 32;
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionKind {
+    Bookmark,
+    History,
+}
+
+#[derive(Deserialize)]
+pub struct SuggestQuery {
+    input: String,
+    history: bool,
+    bookmarks: bool,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct Suggestion {
+    kind: SuggestionKind,
+    url: String,
+    title: String,
+    score: f64,
+}
+
+// An address the input can be completed to inline: `text` is the address as
+// shown, without the scheme, and starts with what was typed.
+#[derive(Serialize)]
+pub struct Autocomplete {
+    text: String,
+    url: String,
+}
+
+#[derive(Serialize)]
+pub struct Suggestions {
+    suggestions: Vec<Suggestion>,
+    autocomplete: Option<Autocomplete>,
+}
+
+// A URL as found in history, bookmarks or both.
+struct Source {
+    url: String,
+    title: String,
+    frecency: f64,
+    visited: bool,
+    bookmarked: bool,
+}
+
+// A run of letters and digits in an entry's haystack, by char position.
+struct Word {
+    start: u32,
+    len: u32,
+    mask: u64,
+}
+
+struct Entry {
+    url: String,
+    title: String,
+    // The address as shown, without the scheme.
+    address: String,
+    // The title and address, lowercased and separated by a space.
+    haystack: String,
+    address_start: usize,
+    chars: Box<[char]>,
+    words: Box<[Word]>,
+    mask: u64,
+    frecency: f64,
+    visited: bool,
+    bookmarked: bool,
+}
+
+impl Entry {
+    fn new(source: Source) -> Self {
+        let Source { url, title, frecency, visited, bookmarked } = source;
+        let shown = WebUrl::parse(&url).map(|u| u.display()).unwrap_or_else(|_| url.clone());
+        let address = shown.split_once("://").map_or(shown.as_str(), |(_, rest)| rest).to_string();
+        let title_lower = title.to_lowercase();
+        let haystack = format!("{title_lower} {}", address.to_lowercase());
+        let chars: Box<[char]> = haystack.chars().collect();
+        let mut words = Vec::new();
+        let mut start = None;
+        for (i, c) in chars.iter().chain([&' ']).enumerate() {
+            match (c.is_alphanumeric(), start) {
+                (true, None) => start = Some(i),
+                (false, Some(from)) => {
+                    words.push(Word {
+                        start: from as u32,
+                        len: (i - from) as u32,
+                        mask: char_mask(chars[from..i].iter().copied()),
+                    });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        Self {
+            address_start: title_lower.len() + 1,
+            mask: char_mask(chars.iter().copied()),
+            url,
+            title,
+            address,
+            haystack,
+            chars,
+            words: words.into(),
+            frecency,
+            visited,
+            bookmarked,
+        }
+    }
+
+    fn address_lower(&self) -> &str {
+        &self.haystack[self.address_start..]
+    }
+
+    fn included(&self, query: &SuggestQuery) -> bool {
+        (query.history && self.visited) || (query.bookmarks && self.bookmarked)
+    }
+
+    // How well every token matches, from 0 (one of them does not) upwards.
+    fn relevance(&self, input: &str, tokens: &[Token]) -> f64 {
+        let mut total = 0.0;
+        for token in tokens {
+            if token.missing(self.mask) > token.max_edits {
+                return 0.0;
+            }
+            match self.token_match(token) {
+                Some(quality) => total += quality,
+                None => return 0.0,
+            }
+        }
+        let mut relevance = total / tokens.len() as f64;
+        if self.address_lower().starts_with(input) {
+            relevance += 0.5;
+        }
+        relevance
+    }
+
+    // Relevance weighed by how much the address is used; 0 if it does not match.
+    fn score(&self, input: &str, tokens: &[Token]) -> f64 {
+        self.relevance(input, tokens) * (1.0 + self.frecency.ln_1p())
+    }
+
+    // Best first: the start of the address, the start of a word, anywhere,
+    // and finally a word a few typos away.
+    fn token_match(&self, token: &Token) -> Option<f64> {
+        if self.address_lower().starts_with(&token.text) {
+            return Some(1.0);
+        }
+        let mut found = None;
+        for (i, _) in self.haystack.match_indices(&token.text) {
+            if !self.haystack[..i].ends_with(char::is_alphanumeric) {
+                return Some(0.8);
+            }
+            found = Some(0.5);
+        }
+        if found.is_some() || token.max_edits == 0 {
+            return found;
+        }
+        self.words
+            .iter()
+            .filter(|w| token.missing(w.mask) <= token.max_edits)
+            .filter_map(|w| {
+                let word = &self.chars[w.start as usize..(w.start + w.len) as usize];
+                prefix_distance(&token.chars, word, token.max_edits)
+            })
+            .min()
+            .map(|edits| 0.4 - 0.1 * edits as f64)
+    }
+}
+
+struct Token {
+    text: String,
+    chars: Vec<char>,
+    // The `char_mask` bit of each char.
+    bits: Vec<u64>,
+    max_edits: usize,
+}
+
+impl Token {
+    fn new(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let max_edits = match chars.len() {
+            0..=3 => 0,
+            _ if chars.len() > MAX_FUZZY_CHARS => 0,
+            4..=7 => 1,
+            _ => 2,
+        };
+        let bits = chars.iter().map(|&c| char_mask(std::iter::once(c))).collect();
+        Self { text: text.to_string(), chars, bits, max_edits }
+    }
+
+    // How many of the token's chars are missing from text with this mask. Each
+    // needs an edit of its own, so this rules text out without counting edits.
+    fn missing(&self, mask: u64) -> usize {
+        self.bits.iter().filter(|&&bit| mask & bit == 0).count()
+    }
+}
+
+#[derive(Default)]
+struct Index {
+    // The history and bookmark revisions it was built from; a change to
+    // either means a rebuild.
+    revisions: Option<(u64, u64)>,
+    built_at: u64,
+    last_visit: i64,
+    entries: Vec<Entry>,
+    by_url: HashMap<String, usize>,
+}
+
+impl Index {
+    fn build(
+        history: &HistoryStore,
+        bookmarks: &BookmarkStore,
+        revisions: (u64, u64),
+        now: u64,
+    ) -> Result<Self, String> {
+        let mut index = Self { revisions: Some(revisions), built_at: now, ..Self::default() };
+        index.last_visit = history.last_visit_id()?;
+        index.add_visits(history.page_stats(0)?);
+        for (url, title) in bookmarks.urls() {
+            let Some(&i) = index.by_url.get(&url) else {
+                index.insert(Source {
+                    url,
+                    title,
+                    frecency: BOOKMARK_FRECENCY,
+                    visited: false,
+                    bookmarked: true,
+                });
+                continue;
+            };
+            let entry = &index.entries[i];
+            if entry.bookmarked {
+                continue;
+            }
+            // The bookmark's title is the one the user chose.
+            let title = if title.is_empty() { entry.title.clone() } else { title };
+            let frecency = entry.frecency + BOOKMARK_FRECENCY;
+            index.entries[i] =
+                Entry::new(Source { url, title, frecency, visited: true, bookmarked: true });
+        }
+        Ok(index)
+    }
+
+    // Folds in the visits recorded since the index was last brought up to date.
+    fn update(&mut self, history: &HistoryStore, last_visit: i64) -> Result<(), String> {
+        let pages = history.page_stats(self.last_visit)?;
+        self.last_visit = last_visit;
+        self.add_visits(pages);
+        Ok(())
+    }
+
+    fn add_visits(&mut self, pages: Vec<PageStats>) {
+        for page in pages {
+            let Some(&i) = self.by_url.get(&page.url) else {
+                self.insert(Source {
+                    url: page.url,
+                    title: page.title,
+                    frecency: page.frecency,
+                    visited: true,
+                    bookmarked: false,
+                });
+                continue;
+            };
+            let entry = &self.entries[i];
+            let bookmarked = entry.bookmarked;
+            let (title, frecency) = if bookmarked {
+                (entry.title.clone(), page.frecency + BOOKMARK_FRECENCY)
+            } else {
+                (page.title, page.frecency)
+            };
+            self.entries[i] =
+                Entry::new(Source { url: page.url, title, frecency, visited: true, bookmarked });
+        }
+    }
+
+    fn insert(&mut self, source: Source) {
+        self.by_url.insert(source.url.clone(), self.entries.len());
+        self.entries.push(Entry::new(source));
+    }
+}
+
+// Address bar suggestions from history and bookmarks, ranked by frecency. The
+// index is kept in memory: new visits are folded in as they come, anything
+// else that changes either store rebuilds it.
+#[derive(Default)]
+pub struct Suggester {
+    index: Mutex<Index>,
+}
+
+impl Suggester {
+    pub fn suggest(
+        &self,
+        history: &HistoryStore,
+        bookmarks: &BookmarkStore,
+        query: SuggestQuery,
+    ) -> Result<Suggestions, String> {
+        let input = normalize(&query.input);
+        let tokens: Vec<Token> = input.split_whitespace().map(Token::new).collect();
+        if tokens.is_empty() || !(query.history || query.bookmarks) {
+            return Ok(Suggestions { suggestions: Vec::new(), autocomplete: None });
+        }
+
+        let mut index = self.index.lock().unwrap();
+        let revisions = (history.revision(), bookmarks.revision());
+        let now = unix_now_ms();
+        if index.revisions != Some(revisions)
+            || now.saturating_sub(index.built_at) > REBUILD_INTERVAL_MS
+        {
+            *index = Index::build(history, bookmarks, revisions, now)?;
+        } else {
+            let last_visit = history.last_visit_id()?;
+            if last_visit > index.last_visit {
+                index.update(history, last_visit)?;
+            }
+        }
+
+        let mut ranked: Vec<(f64, &Entry)> = index
+            .entries
+            .iter()
+            .filter(|entry| entry.included(&query))
+            .filter_map(|entry| {
+                let score = entry.score(&input, &tokens);
+                (score > 0.0).then_some((score, entry))
+            })
+            .collect();
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        if ranked.len() > limit {
+            ranked.select_nth_unstable_by(limit - 1, |a, b| b.0.total_cmp(&a.0));
+            ranked.truncate(limit);
+        }
+        ranked.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+
+        let autocomplete =
+            if tokens.len() == 1 { autocomplete(&index, &query, &input) } else { None };
+        let suggestions = ranked
+            .into_iter()
+            .map(|(score, entry)| Suggestion {
+                kind: if query.bookmarks && entry.bookmarked {
+                    SuggestionKind::Bookmark
+                } else {
+                    SuggestionKind::History
+                },
+                url: entry.url.clone(),
+                title: entry.title.clone(),
+                score,
+            })
+            .collect();
+        Ok(Suggestions { suggestions, autocomplete })
+    }
+}
+
+// Until a `/` is typed only the node is completed; after that, the most
+// used address that continues the input.
+fn autocomplete(index: &Index, query: &SuggestQuery, input: &str) -> Option<Autocomplete> {
+    let entry = index
+        .entries
+        .iter()
+        .filter(|entry| entry.included(query) && entry.address_lower().starts_with(input))
+        .max_by(|a, b| a.frecency.total_cmp(&b.frecency))?;
+    let (text, url) = match entry.address.find('/') {
+        Some(slash) if !input.contains('/') => {
+            let root = &entry.address[..=slash];
+            (root.to_string(), format!("{SCHEME}://{root}"))
+        }
+        _ => (entry.address.clone(), entry.url.clone()),
+    };
+    (text.len() > input.len()).then_some(Autocomplete { text, url })
+}
+
+fn normalize(input: &str) -> String {
+    let input = input.trim().to_lowercase();
+    match input.split_once("://") {
+        Some((_, rest)) => rest.to_string(),
+        None => input,
+    }
+}
+
+// Which characters appear, one bit for each ASCII letter and digit and the
+// rest sharing the remaining bits; enough to rule out most words before
+// counting edits.
+fn char_mask(chars: impl Iterator<Item = char>) -> u64 {
+    chars.fold(0, |mask, c| {
+        let bit = match c {
+            'a'..='z' => c as u32 - 'a' as u32,
+            '0'..='9' => 26 + c as u32 - '0' as u32,
+            _ => 36 + c as u32 % 28,
+        };
+        mask | 1 << bit
+    })
+}
+
+// The fewest edits that turn `token` into some prefix of `word`, counting a
+// swap of neighbouring characters as one. `None` if more than `max` are needed.
+fn prefix_distance(token: &[char], word: &[char], max: usize) -> Option<usize> {
+    let word = &word[..word.len().min(token.len() + max).min(MAX_FUZZY_CHARS)];
+    let n = word.len();
+    let mut before = [0usize; MAX_FUZZY_CHARS + 1];
+    let mut prev = [0usize; MAX_FUZZY_CHARS + 1];
+    let mut cur = [0usize; MAX_FUZZY_CHARS + 1];
+    for (j, cell) in prev.iter_mut().enumerate().take(n + 1) {
+        *cell = j;
+    }
+    for i in 1..=token.len() {
+        cur[0] = i;
+        for j in 1..=n {
+            let cost = usize::from(token[i - 1] != word[j - 1]);
+            let mut d = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && token[i - 1] == word[j - 2] && token[i - 2] == word[j - 1] {
+                d = d.min(before[j - 2] + 1);
+            }
+            cur[j] = d;
+        }
+        if cur[..=n].iter().all(|&d| d > max) {
+            return None;
+        }
+        before = prev;
+        prev = cur;
+    }
+    prev[..=n].iter().copied().min().filter(|&d| d <= max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    fn distance(token: &str, word: &str, max: usize) -> Option<usize> {
+        prefix_distance(&chars(token), &chars(word), max)
+    }
+
+    fn entry(url: &str, title: &str, frecency: f64) -> Entry {
+        Entry::new(Source {
+            url: url.into(),
+            title: title.into(),
+            frecency,
+            visited: true,
+            bookmarked: false,
+        })
+    }
+
+    fn tokens(input: &str) -> Vec<Token> {
+        input.split_whitespace().map(Token::new).collect()
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} is not {expected}");
+    }
+
+    #[test]
+    fn measures_edits_against_a_prefix_of_the_word() {
+        assert_eq!(distance("git", "github", 0), Some(0));
+        assert_eq!(distance("gitgub", "github", 1), Some(1));
+        assert_eq!(distance("gihtub", "github", 1), Some(1));
+        assert_eq!(distance("gthub", "github", 1), Some(1));
+        assert_eq!(distance("giithub", "github", 1), Some(1));
+        assert_eq!(distance("githubb", "github", 1), Some(1));
+        assert_eq!(distance("gtihbu", "github", 1), None);
+        assert_eq!(distance("gtihbu", "github", 2), Some(2));
+        assert_eq!(distance("docs", "github", 2), None);
+    }
+
+    #[test]
+    fn allows_more_typos_in_longer_tokens() {
+        let max_edits = |text: &str| Token::new(text).max_edits;
+        assert_eq!(max_edits("abc"), 0);
+        assert_eq!(max_edits("abcd"), 1);
+        assert_eq!(max_edits("abcdefg"), 1);
+        assert_eq!(max_edits("abcdefgh"), 2);
+        assert_eq!(max_edits(&"a".repeat(MAX_FUZZY_CHARS + 1)), 0);
+    }
+
+    #[test]
+    fn ranks_where_a_token_matches() {
+        let page = entry("web://node1/docs/getting-started", "Node Docs: Getting Started", 0.0);
+        let quality = |text: &str| page.token_match(&Token::new(text)).unwrap_or_default();
+        assert_near(quality("node1/d"), 1.0);
+        assert_near(quality("start"), 0.8);
+        assert_near(quality("tarted"), 0.5);
+        assert_near(quality("stared"), 0.3);
+        assert_near(quality("startde"), 0.3);
+        assert_near(quality("gettting"), 0.3);
+        assert_near(quality("gettnigg"), 0.2);
+        assert!(page.token_match(&Token::new("xyz")).is_none());
+    }
+
+    #[test]
+    fn needs_every_token_and_favours_the_typed_address() {
+        let page = entry("web://node1/docs", "Node Docs", 0.0);
+        assert_near(page.relevance("docs", &tokens("docs")), 0.8);
+        assert_near(page.relevance("node1", &tokens("node1")), 1.5);
+        assert_near(page.relevance("docs node", &tokens("docs node")), 0.9);
+        assert_near(page.relevance("docs blog", &tokens("docs blog")), 0.0);
+    }
+
+    #[test]
+    fn weighs_relevance_by_frecency() {
+        let rare = entry("web://node1/docs", "Docs", 0.0);
+        let frequent = entry("web://node2/docs", "Docs", 500.0);
+        let input = tokens("docs");
+        assert_near(rare.score("docs", &input), 0.8);
+        assert!(frequent.score("docs", &input) > rare.score("docs", &input));
+        // A much better match still wins over a few more visits.
+        let typed = entry("web://docs/", "Home", 20.0);
+        let other = entry("web://node3/docs", "Docs", 40.0);
+        assert!(typed.score("docs", &input) > other.score("docs", &input));
+    }
+
+    #[test]
+    fn completes_the_node_before_a_slash_is_typed() {
+        let mut index = Index::default();
+        for (url, frecency) in [("web://node1/docs/intro", 50.0), ("web://node1/blog", 10.0)] {
+            index.insert(Source {
+                url: url.into(),
+                title: String::new(),
+                frecency,
+                visited: true,
+                bookmarked: false,
+            });
+        }
+        let query = |input: &str| SuggestQuery {
+            input: input.into(),
+            history: true,
+            bookmarks: false,
+            limit: None,
+        };
+        let complete = |input: &str| {
+            autocomplete(&index, &query(input), &normalize(input)).map(|a| (a.text, a.url))
+        };
+        assert_eq!(complete("no"), Some(("node1/".into(), "web://node1/".into())));
+        assert_eq!(
+            complete("node1/d"),
+            Some(("node1/docs/intro".into(), "web://node1/docs/intro".into())),
+        );
+        assert_eq!(complete("web://node1/b").map(|(text, _)| text), Some("node1/blog".into()));
+        assert_eq!(complete("node1/docs/intro"), None);
+        assert_eq!(complete("other"), None);
+    }
+}
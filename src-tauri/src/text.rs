@@ -0,0 +1,103 @@
+use crate::links::{skip_raw, tag_end, tag_name};
+
+// Keeps the index to a sensible size for very long pages.
+const MAX_TEXT_CHARS: usize = 100_000;
+
+pub struct PageText {
+    pub title: Option<String>,
+    pub body: String,
+}
+
+// The readable text of an HTML page: tags, comments, scripts and styles are
+// dropped, entities decoded and whitespace collapsed.
+pub fn extract(html: &str) -> PageText {
+    let mut title = None;
+    let mut body = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        body.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if !rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!') {
+            body.push('<');
+            rest = &rest[1..];
+            continue;
+        }
+        if let Some(skipped) = skip_raw(rest) {
+            rest = &rest[skipped..];
+            continue;
+        }
+        let end = tag_end(rest);
+        if title.is_none() && tag_name(&rest[..end]).eq_ignore_ascii_case("title") {
+            let inner = &rest[end..];
+            let close = find_ignore_case(inner, "</title").unwrap_or(inner.len());
+            title = Some(collapse(&decode_entities(&inner[..close])));
+            rest = &inner[close..];
+            continue;
+        }
+        // Tags separate words, whether or not they are block elements.
+        body.push(' ');
+        rest = &rest[end..];
+    }
+    body.push_str(rest);
+    PageText { title: title.filter(|t| !t.is_empty()), body: plain(&decode_entities(&body)) }
+}
+
+pub fn plain(text: &str) -> String {
+    collapse(text).chars().take(MAX_TEXT_CHARS).collect()
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Where an ASCII `needle`, written in lowercase, first appears in `haystack`
+// in any case. Saves lowercasing a whole document to search the rest of it.
+pub fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+pub fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                entity => {
+                    let code = entity.strip_prefix('#')?;
+                    let code = match code.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => code.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,360 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::scheme::SCHEME;
+
+pub const DEFAULT_PORT: u16 = 6937;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebUrl {
+    node_id: String,
+    port: u16,
+    path: String,
+    query: Option<String>,
+    fragment: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ParsedUrl {
+    url: String,
+    display: String,
+    node_id: String,
+    port: u16,
+    path: String,
+    query: Option<String>,
+    fragment: Option<String>,
+}
+
+impl WebUrl {
+    // Accepts anything a user might type in the address bar: the scheme,
+    // brackets and port are optional, but whatever is present must be well formed.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err("empty address".into());
+        }
+        // A "://" further along, say in the query, does not make a scheme.
+        let rest = match input.split_once("://") {
+            Some((scheme, rest)) if is_scheme(scheme) => {
+                if !scheme.eq_ignore_ascii_case(SCHEME) {
+                    return Err(format!("unsupported scheme `{scheme}`"));
+                }
+                rest
+            }
+            _ => input,
+        };
+
+        let (rest, fragment) = match rest.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(encode(fragment, is_query_char))),
+            None => (rest, None),
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(encode(query, is_query_char))),
+            None => (rest, None),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "/"),
+        };
+
+        let (node_id, port) = parse_authority(authority)?;
+        Ok(Self {
+            node_id,
+            port,
+            path: remove_dot_segments(&encode(path, is_path_char)),
+            query,
+            fragment,
+        })
+    }
+
+    // Resolves a link found on this page. Returns `None` for references to
+    // other schemes, which are left for the caller to handle.
+    pub fn join(&self, reference: &str) -> Option<Result<Self, String>> {
+        let reference = reference.trim();
+        if let Some((scheme, _)) = reference.split_once(':').filter(|(s, _)| is_scheme(s)) {
+            return scheme.eq_ignore_ascii_case(SCHEME).then(|| Self::parse(reference));
+        }
+        let origin = format!("{SCHEME}://{}", self.authority());
+        let joined = if let Some(rest) = reference.strip_prefix("//") {
+            format!("{SCHEME}://{rest}")
+        } else if reference.is_empty() || reference.starts_with('#') {
+            format!("{}{reference}", self.without_fragment())
+        } else if reference.starts_with('?') {
+            format!("{origin}{}{reference}", self.path)
+        } else if reference.starts_with('/') {
+            format!("{origin}{reference}")
+        } else {
+            let dir = &self.path[..=self.path.rfind('/').unwrap_or(0)];
+            format!("{origin}{dir}{reference}")
+        };
+        Some(Self::parse(&joined))
+    }
+
+    // A redirect without its own fragment keeps the one from the original URL.
+    pub fn inherit_fragment(&mut self, from: &WebUrl) {
+        if self.fragment.is_none() {
+            self.fragment = from.fragment.clone();
+        }
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    pub fn authority(&self) -> String {
+        format!("[{}]:{}", self.node_id, self.port)
+    }
+
+    // What goes on the wire: the fragment never leaves the client.
+    pub fn request_target(&self) -> String {
+        match &self.query {
+            Some(query) => format!("{}?{query}", self.path),
+            None => self.path.clone(),
+        }
+    }
+
+    pub fn without_fragment(&self) -> String {
+        format!("{SCHEME}://{}{}", self.authority(), self.request_target())
+    }
+
+    pub fn display(&self) -> String {
+        let port = if self.port == DEFAULT_PORT { String::new() } else { format!(":{}", self.port) };
+        format!("{SCHEME}://{}{port}{}{}", self.node_id, self.request_target(), self.fragment_suffix())
+    }
+
+    // Where the webview loads this resource from through the `web` scheme
+    // handler. Node ids are not IP literals, so the host goes unbracketed.
+    // Windows and Android only route `http://web.localhost`, so there the
+    // authority becomes the first path segment, always with its port.
+    pub fn frame_url(&self) -> String {
+        let (node_id, port, rest) = (&self.node_id, self.port, self.request_target());
+        let fragment = self.fragment_suffix();
+        if cfg!(any(windows, target_os = "android")) {
+            format!("http://{SCHEME}.localhost/{node_id}:{port}{rest}{fragment}")
+        } else {
+            format!("{SCHEME}://{node_id}:{port}{rest}{fragment}")
+        }
+    }
+
+    fn fragment_suffix(&self) -> String {
+        self.fragment.as_ref().map(|f| format!("#{f}")).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for WebUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.without_fragment(), self.fragment_suffix())
+    }
+}
+
+impl From<&WebUrl> for ParsedUrl {
+    fn from(url: &WebUrl) -> Self {
+        Self {
+            url: url.to_string(),
+            display: url.display(),
+            node_id: url.node_id.clone(),
+            port: url.port,
+            path: url.path.clone(),
+            query: url.query.clone(),
+            fragment: url.fragment.clone(),
+        }
+    }
+}
+
+fn parse_authority(authority: &str) -> Result<(String, u16), String> {
+    let (node_id, port) = match authority.strip_prefix('[') {
+        Some(rest) => {
+            let (node_id, after) = rest
+                .split_once(']')
+                .ok_or_else(|| format!("missing `]` in `{authority}`"))?;
+            match after {
+                "" => (node_id, None),
+                _ => match after.strip_prefix(':') {
+                    Some(port) => (node_id, Some(port)),
+                    None => return Err(format!("unexpected `{after}` after `]`")),
+                },
+            }
+        }
+        None if authority.contains(']') => return Err(format!("unmatched `]` in `{authority}`")),
+        None => match authority.split_once(':') {
+            Some((node_id, port)) => (node_id, Some(port)),
+            None => (authority, None),
+        },
+    };
+
+    // The node id is kept exactly as written: it is case-sensitive, so it is
+    // never case-folded here or anywhere it is compared.
+    if node_id.is_empty() {
+        return Err("missing node id".into());
+    }
+    if let Some(c) = node_id.chars().find(|c| !(c.is_ascii_alphanumeric() || "-._".contains(*c))) {
+        return Err(format!("invalid character `{c}` in node id"));
+    }
+    let port = match port {
+        None => DEFAULT_PORT,
+        Some(port) => match port.parse::<u16>() {
+            Ok(p) if p != 0 => p,
+            _ => return Err(format!("invalid port `{port}`")),
+        },
+    };
+    Ok((node_id.to_string(), port))
+}
+
+fn is_scheme(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+}
+
+fn is_path_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/".contains(&c)
+}
+
+fn is_query_char(c: u8) -> bool {
+    is_path_char(c) || c == b'?'
+}
+
+// Percent-encodes anything outside `allowed`, keeping existing escapes but
+// normalising their hex digits to upper case.
+fn encode(input: &str, allowed: fn(u8) -> bool) -> String {
+    let bytes = input.as_bytes();
+    let mut out = String::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let escape = bytes.get(i + 1..i + 3).filter(|h| h.iter().all(u8::is_ascii_hexdigit));
+        match (b, escape) {
+            (b'%', Some(hex)) => {
+                out.push('%');
+                out.push(hex[0].to_ascii_uppercase() as char);
+                out.push(hex[1].to_ascii_uppercase() as char);
+                i += 3;
+                continue;
+            }
+            _ if b != b'%' && allowed(b) => out.push(b as char),
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+        i += 1;
+    }
+    out
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/').skip(1) {
+        match segment {
+            "." | "%2E" => {}
+            ".." | "%2E%2E" | ".%2E" | "%2E." => {
+                segments.pop();
+            }
+            s => segments.push(s),
+        }
+    }
+    // A trailing dot segment still names a directory.
+    if path.ends_with("/.") || path.ends_with("/..") {
+        segments.push("");
+    }
+    format!("/{}", segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> WebUrl {
+        WebUrl::parse(input).unwrap_or_else(|e| panic!("{input}: {e}"))
+    }
+
+    #[test]
+    fn parses_bracketed_and_bare_hosts() {
+        let bracketed = parse("web://[node1]:7000/a/b?x=1#top");
+        assert_eq!(bracketed.node_id(), "node1");
+        assert_eq!(bracketed.port, 7000);
+        assert_eq!(bracketed.request_target(), "/a/b?x=1");
+        assert_eq!(bracketed.fragment.as_deref(), Some("top"));
+        assert_eq!(parse("web://node1:7000/a/b?x=1#top"), bracketed);
+        assert_eq!(parse("node1:7000/a/b?x=1#top"), bracketed);
+    }
+
+    #[test]
+    fn fills_in_defaults() {
+        let url = parse("node1");
+        assert_eq!(url.port, DEFAULT_PORT);
+        assert_eq!(url.to_string(), format!("web://[node1]:{DEFAULT_PORT}/"));
+        assert_eq!(url.display(), "web://node1/");
+        assert_eq!(parse("WEB://[node1]").to_string(), url.to_string());
+    }
+
+    #[test]
+    fn keeps_node_id_case() {
+        assert_eq!(parse("web://AbC/").node_id(), "AbC");
+    }
+
+    #[test]
+    fn fragment_is_not_sent() {
+        let url = parse("web://node1/page#section");
+        assert_eq!(url.without_fragment(), format!("web://[node1]:{DEFAULT_PORT}/page"));
+        assert_eq!(url.display(), "web://node1/page#section");
+    }
+
+    #[test]
+    fn encodes_and_normalises_paths() {
+        assert_eq!(parse("node1/a b/%c3%a9").request_target(), "/a%20b/%C3%A9");
+        assert_eq!(parse("node1/a/./b/../c").request_target(), "/a/c");
+        assert_eq!(parse("node1/a/b/..").request_target(), "/a/");
+    }
+
+    #[test]
+    fn scheme_only_before_the_authority() {
+        let url = parse("node1/redirect?to=https://example.com");
+        assert_eq!(url.node_id(), "node1");
+        assert_eq!(url.request_target(), "/redirect?to=https://example.com");
+        assert!(parse("node1/#a://b").fragment.is_some());
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        for input in [
+            "",
+            "   ",
+            "https://node1/",
+            "web://",
+            "web://[node1/",
+            "web://node1]/",
+            "web://[node1]x/",
+            "web://node1:0/",
+            "web://node1:70000/",
+            "web://node1:port/",
+            "web://no de/",
+            "web://node@1/",
+        ] {
+            assert!(WebUrl::parse(input).is_err(), "{input:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn joins_references() {
+        let base = parse("web://node1:7000/dir/page.html?q#f");
+        let join = |r: &str| base.join(r).map(|u| u.unwrap().to_string());
+        assert_eq!(join("other.html").unwrap(), "web://[node1]:7000/dir/other.html");
+        assert_eq!(join("../up").unwrap(), "web://[node1]:7000/up");
+        assert_eq!(join("/root").unwrap(), "web://[node1]:7000/root");
+        assert_eq!(join("?x=2").unwrap(), "web://[node1]:7000/dir/page.html?x=2");
+        assert_eq!(join("#g").unwrap(), "web://[node1]:7000/dir/page.html?q#g");
+        assert_eq!(join("//node2/x").unwrap(), format!("web://[node2]:{DEFAULT_PORT}/x"));
+        assert_eq!(join("web://node3/").unwrap(), format!("web://[node3]:{DEFAULT_PORT}/"));
+        assert!(join("https://example.com/").is_none());
+        assert!(join("mailto:someone").is_none());
+    }
+
+    #[test]
+    fn frame_url_has_a_routable_host() {
+        let frame = parse("web://[node1]/a?b#c").frame_url();
+        if cfg!(any(windows, target_os = "android")) {
+            assert_eq!(frame, format!("http://web.localhost/node1:{DEFAULT_PORT}/a?b#c"));
+        } else {
+            assert_eq!(frame, format!("web://node1:{DEFAULT_PORT}/a?b#c"));
+        }
+    }
+}
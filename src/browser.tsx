@@ -2,6 +2,7 @@ import {
   useState,
   useRef,
   useEffect,
+  useLayoutEffect,
   useMemo,
   useCallback,
   forwardRef,
@@ -12,7 +13,7 @@ import {
 } from "react"
 import { createPortal } from "react-dom"
 import { getCurrentWindow } from "@tauri-apps/api/window"
-import { invoke } from "@tauri-apps/api/core"
+import { Channel, invoke } from "@tauri-apps/api/core"
 import { useTheme } from "next-themes"
 import {
   ChevronLeft,
@@ -56,7 +57,8 @@ import {
   BookOpen,
 } from "lucide-react"
 import { cn } from "@/lib/utils"
-import { t } from "@/lib/i18n"
+import { migrateLegacy } from "@/lib/legacy"
+import { t, type TranslationKey } from "@/lib/i18n"
 import { Button } from "@/components/ui/button"
 import {
   DropdownMenu,
@@ -93,8 +95,8 @@ import { CSS } from "@dnd-kit/utilities"
 import {
   BookmarkNode,
   BookmarkTree,
+  BookmarkPatch,
   loadBookmarks,
-  saveBookmarks,
   addNode,
   updateNode,
   removeNode,
@@ -105,12 +107,11 @@ import {
   getFolderTitle,
   reorderInParent,
   moveNodeIntoFolder,
-  genId,
 } from "@/lib/bookmarks"
-import { type BrowserSettings, loadSettings, saveSettings } from "@/lib/settings"
+import { type BrowserSettings, subscribeSettings, updateSettings } from "@/lib/settings"
 import { isOnboardingComplete, markOnboardingComplete, resetOnboarding } from "@/lib/onboarding"
 import { save as saveDialog } from "@tauri-apps/plugin-dialog"
-import { writeTextFile } from "@tauri-apps/plugin-fs"
+import { writeFile, writeTextFile } from "@tauri-apps/plugin-fs"
 import { openUrl } from "@tauri-apps/plugin-opener"
 import { SettingsPage } from "@/components/settings-page"
 import { OnboardingOverlay } from "@/components/onboarding-overlay"
@@ -126,31 +127,69 @@ const MAX_TAB_W = 200
 const MIN_TAB_W = 72
 
 
+interface NwepError {
+  category: "network" | "crypto" | "identity" | "protocol" | "identity_mismatch" | "key_changed" | "invalid_url" | "offline" | "cancelled" | "internal"
+  code: number
+  message: string
+  retryable: boolean
+}
+
 interface LogStep {
   name: string
   ok: boolean
   detail?: string | null
+  error?: NwepError | null
+  offset_ms: number
+  duration_ms: number
 }
 
 interface NwepResult {
   ok: boolean
-  error?: string | null
+  error?: NwepError | null
+  url?: string | null
   status?: string | null
   status_details?: string | null
-  body?: string | null
+  content_type?: string | null
+  text?: string | null
+  body_base64?: string | null
   headers: Array<{ name: string; value: string }>
   connection?: {
     client_node_id: string
     server_node_id: string
     server_pubkey: string
   } | null
+  key_change?: KeyChange | null
+  stale?: StaleInfo | null
+  cancelled: boolean
+  ttfb_ms?: number | null
+  total_ms: number
   log: LogStep[]
 }
 
+interface StaleInfo {
+  age_secs: number
+  error?: NwepError | null
+}
+
+interface KeyChange {
+  node_id: string
+  pinned_pubkey: string
+  presented_pubkey: string
+  first_seen: number
+}
+
+type FetchEvent =
+  | { event: "step"; data: LogStep }
+  | { event: "connecting"; data: { url: string } }
+  | { event: "connected"; data: { reused: boolean } }
+  | { event: "headers_received"; data: { status: string; header_count: number } }
+
 interface ConnectionInfo {
   clientNodeId?: string
   serverNodeId?: string
   serverPubkey?: string
+  ttfbMs?: number
+  totalMs?: number
   log: LogStep[]
 }
 
@@ -159,34 +198,98 @@ interface Tab {
   title: string
   url: string
   content?: string
-  error?: string
+  error?: NwepError
+  keyChange?: KeyChange
+  stale?: StaleInfo
+  download?: PendingDownload
   connectionInfo?: ConnectionInfo
   history: string[]
   historyIndex: number
 }
 
+// A response the browser cannot display. It is only written to disk once the
+// user asks for it.
+interface PendingDownload {
+  name: string
+  contentType: string
+  bodyBase64: string
+}
+
 interface HistoryEntry {
+  id?: number
   url: string
   title: string
   timestamp?: number
 }
 
+type Transition = "link" | "typed" | "back_forward" | "reload" | "start_page"
+
+interface Visit {
+  id: number
+  url: string
+  title: string
+  visited_at: number
+  transition: Transition | "imported"
+  tab_id?: string | null
+}
+
+interface HistoryPageResult {
+  visits: Visit[]
+  next_cursor?: number | null
+}
+
 interface Suggestion {
-  type: "bookmark" | "history"
+  kind: "bookmark" | "history"
   url: string
   title: string
 }
 
+interface SuggestResult {
+  suggestions: Suggestion[]
+  // An address that continues the input; `text` omits the scheme.
+  autocomplete: { text: string; url: string } | null
+}
+
+const NO_SUGGESTIONS: SuggestResult = { suggestions: [], autocomplete: null }
+
+// Ranked in Rust from history and bookmarks. Answers that arrive after the
+// input has moved on are dropped.
+function useSuggestions(input: string, bookmarks: boolean, history: boolean): SuggestResult {
+  const [result, setResult] = useState<SuggestResult>(NO_SUGGESTIONS)
+  useEffect(() => {
+    if (!input.trim()) { setResult(NO_SUGGESTIONS); return }
+    let stale = false
+    invoke<SuggestResult>("suggest", { query: { input, bookmarks, history } })
+      .then((found) => { if (!stale) setResult(found) })
+      .catch(console.error)
+    return () => { stale = true }
+  }, [input, bookmarks, history])
+  return result
+}
+
+// What inline autocomplete adds after the typed text, if the candidate still
+// continues it.
+function completionTail(draft: string, autocomplete: SuggestResult["autocomplete"]): string {
+  if (!draft || !autocomplete) return ""
+  const typed = draft.toLowerCase()
+  for (const full of [autocomplete.text, `web://${autocomplete.text}`]) {
+    if (full.length > draft.length && full.toLowerCase().startsWith(typed)) return full.slice(draft.length)
+  }
+  return ""
+}
+
 
-const HISTORY_KEY = "nwep-history-v1"
-const MAX_HISTORY = 128
+const LEGACY_HISTORY_KEY = "nwep-history-v1"
+const RECENT_HISTORY_LIMIT = 500
 
-function loadGlobalHistory(): HistoryEntry[] {
-  try { return JSON.parse(localStorage.getItem(HISTORY_KEY) ?? "[]") } catch { return [] }
+function visitToEntry(visit: Visit): HistoryEntry {
+  return { id: visit.id, url: visit.url, title: visit.title, timestamp: visit.visited_at }
 }
 
-function saveGlobalHistory(entries: HistoryEntry[]): void {
-  localStorage.setItem(HISTORY_KEY, JSON.stringify(entries))
+async function loadGlobalHistory(): Promise<HistoryEntry[]> {
+  await migrateLegacy<number>(LEGACY_HISTORY_KEY, "migrate_history", "entries")
+  const visits = await invoke<Visit[]>("recent_history", { limit: RECENT_HISTORY_LIMIT })
+  return visits.map(visitToEntry)
 }
 
 
@@ -416,68 +519,34 @@ function TabBar({
 }
 
 
-const NWEP_DEFAULT_PORT = "6937"
-
-
-function prettyNwepUrl(url: string): string {
-  if (!url.startsWith("web://")) return url
-  const rest = url.slice("web://".length)
-  if (!rest.startsWith("[")) return url
-  const close = rest.indexOf("]")
-  if (close === -1) return url
-  const nodeId = rest.slice(1, close)
-  const afterBracket = rest.slice(close + 1) // e.g. ":6937/path" or "/path"
-  const slashIdx = afterBracket.indexOf("/")
-  const portPart = slashIdx === -1 ? afterBracket : afterBracket.slice(0, slashIdx)
-  const path = slashIdx === -1 ? "/" : afterBracket.slice(slashIdx)
-  const port = portPart.startsWith(":") ? portPart.slice(1) : NWEP_DEFAULT_PORT
-  const portSuffix = port === NWEP_DEFAULT_PORT ? "" : `:${port}`
-  return `web://${nodeId}${portSuffix}${path}`
+interface ParsedUrl {
+  url: string
+  display: string
+  node_id: string
+  port: number
+  path: string
+  query?: string | null
+  fragment?: string | null
 }
 
-function normalizeNwepUrl(raw: string): string {
-  let s = raw.trim()
-  if (!s.includes("://")) s = `web://${s}`
-
-  const schemeEnd = s.indexOf("://") + 3
-  const rest = s.slice(schemeEnd)
-
-  let authority: string
-  let path: string
-
-  if (rest.startsWith("[")) {
-    const close = rest.indexOf("]")
-    if (close === -1) return `web://[${rest.slice(1)}]:6937/`
-    const afterBracket = rest.slice(close + 1)
-    const slashIdx = afterBracket.indexOf("/")
-    authority = rest.slice(0, close + 1) + (slashIdx === -1 ? afterBracket : afterBracket.slice(0, slashIdx))
-    path = slashIdx === -1 ? "/" : afterBracket.slice(slashIdx)
-  } else {
-    const slashIdx = rest.indexOf("/")
-    authority = slashIdx === -1 ? rest : rest.slice(0, slashIdx)
-    path = slashIdx === -1 ? "/" : rest.slice(slashIdx)
-  }
-
-  let nodeId: string
-  let port: string
-
-  if (authority.startsWith("[")) {
-    const close = authority.indexOf("]")
-    nodeId = authority.slice(1, close)
-    const portPart = authority.slice(close + 1)
-    port = portPart.startsWith(":") ? portPart.slice(1) : "6937"
-  } else {
-    const colonIdx = authority.indexOf(":")
-    if (colonIdx === -1) {
-      nodeId = authority
-      port = "6937"
-    } else {
-      nodeId = authority.slice(0, colonIdx)
-      port = authority.slice(colonIdx + 1)
-    }
-  }
+// The display form is looked up ahead of time, because copying it has to
+// happen while the click that asked for it is still being handled.
+function useDisplayUrl(url: string): string {
+  const [display, setDisplay] = useState({ url, text: url })
+  useEffect(() => {
+    let live = true
+    invoke<ParsedUrl>("parse_url", { url })
+      .then((parsed) => { if (live) setDisplay({ url, text: parsed.display }) })
+      .catch(() => {})
+    return () => { live = false }
+  }, [url])
+  return display.url === url ? display.text : url
+}
 
-  return `web://[${nodeId}]:${port}${path}`
+// Unparseable input is passed through so the fetch reports why it is invalid.
+function normalizeAddress(input: string): Promise<string> {
+  if (input.startsWith("about:")) return Promise.resolve(input)
+  return invoke<string>("normalize_url", { url: input }).catch(() => input)
 }
 
 function getDisplayHost(url: string): string {
@@ -520,7 +589,7 @@ function SuggestionItem({
           : "hover:bg-black/[0.03] dark:hover:bg-white/[0.04]",
       )}
     >
-      {suggestion.type === "bookmark"
+      {suggestion.kind === "bookmark"
         ? <Bookmark className="size-3.5 shrink-0 text-foreground/40" />
         : <History className="size-3.5 shrink-0 text-foreground/40" />
       }
@@ -537,8 +606,6 @@ function SuggestionItem({
 function UrlBar({
   url,
   onNavigate,
-  bookmarks,
-  globalHistory,
   searchBookmarks: enableSearchBookmarks,
   searchHistory: enableSearchHistory,
   connectionInfo,
@@ -546,8 +613,6 @@ function UrlBar({
 }: {
   url: string
   onNavigate: (url: string) => void
-  bookmarks: BookmarkTree
-  globalHistory: HistoryEntry[]
   searchBookmarks: boolean
   searchHistory: boolean
   connectionInfo?: ConnectionInfo
@@ -556,11 +621,15 @@ function UrlBar({
   const [draft, setDraft] = useState(url)
   const [focused, setFocused] = useState(false)
   const [urlCopied, setUrlCopied] = useState(false)
+  const shownUrl = useDisplayUrl(url)
   const copyUrl = (e: React.MouseEvent) => {
     e.stopPropagation()
-    navigator.clipboard.writeText(prettyNwepUrl(url)).catch(() => {})
-    setUrlCopied(true)
-    setTimeout(() => setUrlCopied(false), 1500)
+    navigator.clipboard.writeText(shownUrl)
+      .then(() => {
+        setUrlCopied(true)
+        setTimeout(() => setUrlCopied(false), 1500)
+      })
+      .catch(console.error)
   }
   const [selectedIdx, setSelectedIdx] = useState(-1)
   const inputRef = useRef<HTMLInputElement>(null)
@@ -569,31 +638,14 @@ function UrlBar({
 
   const isEmpty = !url || url === "about:newtab"
 
-  const flatBks = useMemo(() => flatBookmarks(bookmarks), [bookmarks])
-
-  const suggestions = useMemo<Suggestion[]>(() => {
-    const q = draft.trim().toLowerCase()
-    if (!q) return []
-    const results: Suggestion[] = []
-    if (enableSearchBookmarks) {
-      for (const b of flatBks) {
-        if (b.url.toLowerCase().includes(q) || b.title.toLowerCase().includes(q)) {
-          results.push({ type: "bookmark", url: b.url, title: b.title })
-          if (results.length >= 5) break
-        }
-      }
-    }
-    if (enableSearchHistory) {
-      const seen = new Set(results.map((r) => r.url))
-      for (const h of globalHistory) {
-        if (!seen.has(h.url) && (h.url.toLowerCase().includes(q) || h.title.toLowerCase().includes(q))) {
-          results.push({ type: "history", url: h.url, title: h.title })
-          if (results.length >= 8) break
-        }
-      }
-    }
-    return results
-  }, [draft, enableSearchBookmarks, enableSearchHistory, flatBks, globalHistory])
+  const { suggestions, autocomplete } = useSuggestions(focused ? draft : "", enableSearchBookmarks, enableSearchHistory)
+  // Only offered while typing forwards; deleting would otherwise bring it straight back.
+  const [inlineAllowed, setInlineAllowed] = useState(false)
+  const inline = focused && inlineAllowed ? completionTail(draft, autocomplete) : ""
+
+  useLayoutEffect(() => {
+    if (inline) inputRef.current?.setSelectionRange(draft.length, draft.length + inline.length)
+  }, [draft, inline])
 
   const showSuggestions = focused && suggestions.length > 0
 
@@ -632,26 +684,35 @@ function UrlBar({
       doNavigate(suggestions[selectedIdx].url)
       return
     }
+    if (inline && autocomplete) {
+      doNavigate(autocomplete.url)
+      return
+    }
     const trimmed = draft.trim()
     if (!trimmed) return
-    doNavigate(trimmed.startsWith("about:") ? trimmed : normalizeNwepUrl(trimmed))
+    normalizeAddress(trimmed).then(doNavigate)
   }
 
   const handleFocus = () => {
     setDraft(isEmpty ? "" : url)
+    setInlineAllowed(false)
     setFocused(true)
     setTimeout(() => inputRef.current?.select(), 0)
   }
 
   const handleKeyDown = (e: React.KeyboardEvent<HTMLInputElement>) => {
+    if (inline && (e.key === "ArrowRight" || e.key === "End")) {
+      setDraft(draft + inline)
+      setInlineAllowed(false)
+    }
     if (!showSuggestions) return
     if (e.key === "ArrowDown") { e.preventDefault(); setSelectedIdx((i) => Math.min(i + 1, suggestions.length - 1)) }
     else if (e.key === "ArrowUp") { e.preventDefault(); setSelectedIdx((i) => Math.max(i - 1, -1)) }
     else if (e.key === "Escape") { setSelectedIdx(-1); setFocused(false); inputRef.current?.blur() }
   }
 
-  const bkSuggestions = suggestions.filter((s) => s.type === "bookmark")
-  const histSuggestions = suggestions.filter((s) => s.type === "history")
+  const bkSuggestions = suggestions.filter((s) => s.kind === "bookmark")
+  const histSuggestions = suggestions.filter((s) => s.kind === "history")
 
   return (
     <form ref={formRef} onSubmit={handleSubmit} className="flex-1 min-w-0">
@@ -679,8 +740,12 @@ function UrlBar({
         <input
           ref={inputRef}
           type="text"
-          value={focused ? draft : getDisplayHost(url)}
-          onChange={(e) => { setDraft(e.target.value); setSelectedIdx(-1) }}
+          value={focused ? draft + inline : getDisplayHost(url)}
+          onChange={(e) => {
+            setInlineAllowed((e.nativeEvent as InputEvent).inputType?.startsWith("insert") ?? false)
+            setDraft(e.target.value)
+            setSelectedIdx(-1)
+          }}
           onFocus={handleFocus}
           onBlur={() => setFocused(false)}
           onKeyDown={handleKeyDown}
@@ -786,8 +851,13 @@ function IdentityRow({ label, nodeId, pubkey, note }: {
   )
 }
 
+function formatMs(ms: number): string {
+  return ms < 10 ? `${ms.toFixed(1)} ms` : `${Math.round(ms)} ms`
+}
+
 function VerificationBadge({ info, compact = false }: { info: ConnectionInfo; compact?: boolean }) {
   const allOk = info.log.every((s) => s.ok)
+  const span = Math.max(info.totalMs ?? 0, ...info.log.map((s) => s.offset_ms + s.duration_ms), 1)
   const [open, setOpen] = useState(false)
   const triggerCls = compact
     ? "flex items-center justify-center outline-none shrink-0 rounded focus-visible:ring-2 focus-visible:ring-ring/50"
@@ -836,22 +906,40 @@ function VerificationBadge({ info, compact = false }: { info: ConnectionInfo; co
                   ? <Check className="size-3 text-green-500 dark:text-green-400 shrink-0 mt-[1px]" />
                   : <X className="size-3 text-red-500 dark:text-red-400 shrink-0 mt-[1px]" />
                 }
-                <div className="min-w-0">
-                  <p className="text-[11.5px] font-mono text-foreground/80 leading-snug">{step.name}</p>
+                <div className="min-w-0 flex-1">
+                  <div className="flex items-baseline justify-between gap-2">
+                    <p className="text-[11.5px] font-mono text-foreground/80 leading-snug">{step.name}</p>
+                    <p className="text-[10.5px] font-mono text-foreground/40 shrink-0">{formatMs(step.duration_ms)}</p>
+                  </div>
                   {step.detail && (
                     <p className="text-[10.5px] font-mono text-foreground/40 leading-snug break-all">{step.detail}</p>
                   )}
+                  <div className="relative h-[3px] mt-1 rounded-full bg-foreground/[0.06]">
+                    <div
+                      className={cn("absolute inset-y-0 rounded-full", step.ok ? "bg-green-500/60" : "bg-red-500/60")}
+                      style={{
+                        left: `${(step.offset_ms / span) * 100}%`,
+                        width: `max(2px, ${(step.duration_ms / span) * 100}%)`,
+                      }}
+                    />
+                  </div>
                 </div>
               </div>
             ))}
           </div>
+          {info.totalMs != null && (
+            <p className="mt-2 text-[10.5px] font-mono text-foreground/40">
+              {info.ttfbMs != null && `${t("connection.ttfb")} ${formatMs(info.ttfbMs)} · `}
+              {t("connection.total")} {formatMs(info.totalMs)}
+            </p>
+          )}
         </div>
 
 
         {info.serverNodeId && (
           <div className="px-3.5 py-2.5 space-y-3">
             <IdentityRow label={t("connection.server")} nodeId={info.serverNodeId} pubkey={info.serverPubkey} />
-            {info.clientNodeId && <IdentityRow label={t("connection.client")} nodeId={info.clientNodeId} note={t("connection.ephemeral")} />}
+            {info.clientNodeId && <IdentityRow label={t("connection.client")} nodeId={info.clientNodeId} />}
           </div>
         )}
 
@@ -1094,6 +1182,10 @@ function BookmarksMenu({
   const parentId = path.length > 0 ? path[path.length - 1] : null
   const bkActiveNode = bkActiveId ? currentItems.find((n) => n.id === bkActiveId) ?? null : null
 
+  const applyChange = (change: Promise<BookmarkTree>) => {
+    change.then(onTreeChange).catch(console.error)
+  }
+
   const resetState = () => {
     setPath([])
     setEditingId(null)
@@ -1119,21 +1211,20 @@ function BookmarksMenu({
   }
   const commitEdit = () => {
     if (!editingId || !editTitle.trim()) { setEditingId(null); return }
-    const patch: Partial<BookmarkNode> = { title: editTitle.trim() }
+    const patch: BookmarkPatch = { title: editTitle.trim() }
     if (editUrl.trim()) patch.url = editUrl.trim()
-    onTreeChange(updateNode(bookmarks, editingId, patch))
+    applyChange(updateNode(editingId, patch))
     setEditingId(null)
   }
   const handleDelete = (id: string) => {
-    onTreeChange(removeNode(bookmarks, id))
+    applyChange(removeNode(id))
     if (editingId === id) setEditingId(null)
   }
   const handleAdd = () => {
     if (!addTitle.trim()) return
-    const node: BookmarkNode = addMode === "folder"
-      ? { id: genId(), type: "folder", title: addTitle.trim(), children: [] }
-      : { id: genId(), type: "bookmark", title: addTitle.trim(), url: addUrl.trim() || undefined }
-    onTreeChange(addNode(bookmarks, parentId, node))
+    applyChange(addNode(parentId, addMode === "folder"
+      ? { type: "folder", title: addTitle.trim() }
+      : { type: "bookmark", title: addTitle.trim(), url: addUrl.trim() || undefined }))
     setAddTitle(""); setAddUrl(""); setAddMode("none")
   }
   const toggleAddMode = (mode: "bookmark" | "folder") => {
@@ -1161,15 +1252,15 @@ function BookmarksMenu({
 
     if (over?.id === PARENT_DROP_ID && path.length > 0) {
       const grandParentId = path.length > 1 ? path[path.length - 2] : null
-      onTreeChange(moveNodeIntoFolder(bookmarks, active.id as string, grandParentId))
+      applyChange(moveNodeIntoFolder(active.id as string, grandParentId))
       navBreadcrumb(path.length - 1)
     } else if (folderId && folderId !== (active.id as string)) {
-      onTreeChange(moveNodeIntoFolder(bookmarks, active.id as string, folderId))
+      applyChange(moveNodeIntoFolder(active.id as string, folderId))
     } else if (over && active.id !== over.id) {
       const oldIdx = currentItems.findIndex((n) => n.id === active.id)
       const newIdx = currentItems.findIndex((n) => n.id === over.id)
       if (oldIdx !== -1 && newIdx !== -1) {
-        onTreeChange(reorderInParent(bookmarks, parentId, oldIdx, newIdx))
+        applyChange(reorderInParent(parentId, oldIdx, newIdx))
       }
     }
     setBkActiveId(null)
@@ -1520,7 +1611,6 @@ function NavBar({
   onTreeChange,
   onOpenSettings,
   onOpenHistory,
-  globalHistory,
   searchBookmarks,
   searchHistory,
   connectionInfo,
@@ -1554,7 +1644,6 @@ function NavBar({
   onTreeChange: (tree: BookmarkTree) => void
   onOpenSettings: () => void
   onOpenHistory: () => void
-  globalHistory: HistoryEntry[]
   searchBookmarks: boolean
   searchHistory: boolean
   connectionInfo?: ConnectionInfo
@@ -1580,8 +1669,6 @@ function NavBar({
       <UrlBar
         url={url}
         onNavigate={onNavigate}
-        bookmarks={bookmarks}
-        globalHistory={globalHistory}
         searchBookmarks={searchBookmarks}
         searchHistory={searchHistory}
         connectionInfo={connectionInfo}
@@ -1766,10 +1853,17 @@ interface ContentFrameHandle {
   clearSearch(): void
 }
 
-const ContentFrame = forwardRef<ContentFrameHandle, { url: string; content: string; zoom: number }>(
-  function ContentFrame({ url, content, zoom }, ref) {
+const ContentFrame = forwardRef<ContentFrameHandle, {
+  url: string
+  content: string
+  zoom: number
+  onFollowLink: (href: string, newTab: boolean) => void
+}>(
+  function ContentFrame({ url, content, zoom, onFollowLink }, ref) {
     const { resolvedTheme } = useTheme()
     const iframeRef = useRef<HTMLIFrameElement>(null)
+    const onFollowLinkRef = useRef(onFollowLink)
+    onFollowLinkRef.current = onFollowLink
     const findState = useRef<{ marks: HTMLElement[]; idx: number }>({ marks: [], idx: 0 })
 
     const setActive = (idx: number) => {
@@ -1842,13 +1936,53 @@ const ContentFrame = forwardRef<ContentFrameHandle, { url: string; content: stri
           },0);
         },true);
       }())<\/script>`
-      const inject = defaults + ctxScript
+      // Links are made absolute by the backend; clicks and form submissions are
+      // handed to the browser so they become normal tab navigations. The page's
+      // <base> points at the scheme handler, so in-page anchors are scrolled to
+      // here rather than followed.
+      const navScript = `<script>(function(){
+        function go(h,n){window.parent.postMessage({type:'nwep-navigate',href:h,newTab:n},'*')}
+        document.addEventListener('click',function(e){
+          if(e.defaultPrevented||e.button!==0)return;
+          var a=e.target;while(a&&a.tagName!=='A'&&a.tagName!=='AREA')a=a.parentElement;
+          if(!a)return;
+          var h=a.getAttribute('href');
+          if(h===null)return;
+          e.preventDefault();
+          if(h.charAt(0)==='#'){
+            var id=decodeURIComponent(h.slice(1)),el=id&&(document.getElementById(id)||document.getElementsByName(id)[0]);
+            if(el)el.scrollIntoView();else if(!id||id==='top')window.scrollTo(0,0);
+            return;
+          }
+          go(h,e.ctrlKey||e.metaKey||a.target==='_blank');
+        });
+        document.addEventListener('submit',function(e){
+          if(e.defaultPrevented)return;
+          e.preventDefault();
+          var f=e.target,q=new URLSearchParams(new FormData(f)).toString();
+          var h=(f.getAttribute('action')||'').split('#')[0];
+          go(q?h.split('?')[0]+'?'+q:h,f.target==='_blank');
+        });
+      }())<\/script>`
+      const inject = defaults + ctxScript + navScript
       if (/<head[\s>]/i.test(content)) {
         return content.replace(/<head([\s>])/i, `<head$1${inject}`)
       }
       return inject + content
     }, [content, resolvedTheme])
 
+    // Subresource loads still running for the document being replaced are
+    // cancelled before the new one is shown, so they cannot hold up its own.
+    const [readyDoc, setReadyDoc] = useState<string | null>(null)
+    useEffect(() => {
+      let live = true
+      invoke("cancel_frame_loads")
+        .catch(() => {})
+        .finally(() => { if (live) setReadyDoc(srcDoc) })
+      return () => { live = false }
+    }, [srcDoc])
+    useEffect(() => () => { invoke("cancel_frame_loads").catch(() => {}) }, [])
+
     useEffect(() => {
       const iframe = iframeRef.current
       if (!iframe) return
@@ -1863,6 +1997,15 @@ const ContentFrame = forwardRef<ContentFrameHandle, { url: string; content: stri
       return () => iframe.removeEventListener("load", apply)
     }, [zoom])
 
+    useEffect(() => {
+      const handler = (e: MessageEvent) => {
+        if (e.data?.type !== "nwep-navigate" || e.source !== iframeRef.current?.contentWindow) return
+        onFollowLinkRef.current(String(e.data.href ?? ""), !!e.data.newTab)
+      }
+      window.addEventListener("message", handler)
+      return () => window.removeEventListener("message", handler)
+    }, [])
+
     const pullCtx = useContext(PullRefreshContext)
 
     useEffect(() => {
@@ -1931,7 +2074,7 @@ const ContentFrame = forwardRef<ContentFrameHandle, { url: string; content: stri
       <iframe
         key={url}
         ref={iframeRef}
-        srcDoc={srcDoc}
+        srcDoc={readyDoc === srcDoc ? srcDoc : ""}
         className="flex-1 min-h-0 w-full border-none"
         title="Page content"
       />
@@ -1940,11 +2083,87 @@ const ContentFrame = forwardRef<ContentFrameHandle, { url: string; content: stri
 )
 
 
-function ErrorPage({ error, url, onRetry }: { error: string; url: string; onRetry: () => void }) {
-  const match = error.match(/^\[(\w+):(-?\d+)\]\s*(.*)/)
-  const category = match?.[1] ?? null
-  const code     = match?.[2] ?? null
-  const message  = match?.[3] ?? error
+function DownloadPage({ download }: { download: PendingDownload }) {
+  const [saved, setSaved] = useState<boolean | null>(null)
+  const size = Math.floor(download.bodyBase64.length * 3 / 4)
+    - (download.bodyBase64.match(/=*$/)?.[0].length ?? 0)
+
+  const save = async () => {
+    try {
+      const path = await saveDialog({ title: t("download.title"), defaultPath: download.name })
+      if (!path) return
+      await writeFile(path, decodeBase64(download.bodyBase64))
+      setSaved(true)
+    } catch (err) {
+      console.error(err)
+      setSaved(false)
+    }
+  }
+
+  return (
+    <div className="flex-1 overflow-y-auto bg-white dark:bg-[#1c1c1e] select-none">
+      <div className="flex items-center min-h-full py-16 ps-[max(48px,10%)] pe-8">
+        <div className="max-w-[520px] w-full">
+          <Download className="size-[52px] mb-8 text-[#1a1a1b] dark:text-[#2f2f31]" />
+          <h1 className="text-[30px] font-bold text-foreground tracking-[-0.01em] leading-tight mb-3 break-all">
+            {download.name}
+          </h1>
+          <p className="text-[15px] text-foreground/55 leading-relaxed mb-8 max-w-[420px]">
+            {t("download.available", {
+              type: download.contentType.split(";")[0].trim(),
+              size: (size / 1024).toFixed(1),
+            })}
+          </p>
+          <button
+            onClick={save}
+            className={cn(
+              "px-5 py-2 rounded-md text-[13px] font-medium transition-colors",
+              "bg-black/[0.05] dark:bg-white/[0.07] hover:bg-black/[0.09] dark:hover:bg-white/[0.11]",
+              "text-foreground/80 hover:text-foreground",
+            )}
+          >
+            {t("download.save")}
+          </button>
+          {saved != null && (
+            <p className="mt-4 text-[12.5px] text-foreground/45">
+              {t(saved ? "download.saved" : "download.notSaved", { name: download.name })}
+            </p>
+          )}
+        </div>
+      </div>
+    </div>
+  )
+}
+
+function formatAge(secs: number): string {
+  if (secs < 60) return t("offline.seconds", { n: secs })
+  if (secs < 3600) return t("offline.minutes", { n: Math.floor(secs / 60) })
+  if (secs < 86400) return t("offline.hours", { n: Math.floor(secs / 3600) })
+  return t("offline.days", { n: Math.floor(secs / 86400) })
+}
+
+function StaleBanner({ stale }: { stale: StaleInfo }) {
+  return (
+    <div className="shrink-0 flex items-center gap-2 px-3 py-1.5 text-[12px] bg-amber-500/10 text-amber-700 dark:text-amber-400 border-b border-amber-500/20 select-none">
+      <WifiOff className="size-3.5 shrink-0" />
+      <span className="shrink-0">{t("offline.savedCopy", { age: formatAge(stale.age_secs) })}</span>
+      {stale.error && (
+        <span className="font-mono text-[11px] opacity-70 truncate">
+          [{stale.error.category}:{stale.error.code}] {stale.error.message}
+        </span>
+      )}
+    </div>
+  )
+}
+
+function ErrorPage({ error, url, keyChange, onRetry, onTrust }: {
+  error: NwepError
+  url: string
+  keyChange?: KeyChange
+  onRetry: () => void
+  onTrust: (change: KeyChange) => void
+}) {
+  const { category, code, message } = error
 
   type Cfg = { Icon: React.ComponentType<{ className?: string }>; title: string; hint: string }
   const { Icon, title, hint }: Cfg = (() => {
@@ -1961,6 +2180,14 @@ function ErrorPage({ error, url, onRetry }: { error: string; url: string; onRetr
         return { Icon: Unplug, title: t("error.serverNotFound"), hint: t("error.serverNotFoundHint") }
       return { Icon: ShieldAlert, title: t("error.identityError"), hint: t("error.identityErrorHint") }
     }
+    if (category === "identity_mismatch")
+      return { Icon: ShieldAlert, title: t("error.identityMismatch"), hint: t("error.identityMismatchHint") }
+    if (category === "key_changed")
+      return { Icon: ShieldAlert, title: t("error.keyChanged"), hint: t("error.keyChangedHint") }
+    if (category === "offline")
+      return { Icon: WifiOff, title: t("error.offline"), hint: t("error.offlineHint") }
+    if (category === "invalid_url")
+      return { Icon: CircleAlert, title: t("error.invalidUrl"), hint: t("error.invalidUrlHint") }
     if (category === "protocol")
       return { Icon: ServerCrash, title: t("error.protocolError"), hint: t("error.protocolErrorHint") }
     return { Icon: CircleAlert, title: t("error.genericError"), hint: t("error.genericErrorHint") }
@@ -1991,11 +2218,30 @@ function ErrorPage({ error, url, onRetry }: { error: string; url: string; onRetr
             {t("error.tryAgain")}
           </button>
 
+          {keyChange && (
+            <div className="mt-8 space-y-2">
+              <p className="text-[12px] text-foreground/45">
+                {t("error.keyPinnedSince", { date: new Date(keyChange.first_seen * 1000).toLocaleDateString() })}
+              </p>
+              <p className="font-mono text-[11.5px] text-foreground/40 break-all">
+                <span className="text-foreground/25">{t("error.keyPinned")}</span> {keyChange.pinned_pubkey}
+              </p>
+              <p className="font-mono text-[11.5px] text-foreground/40 break-all">
+                <span className="text-foreground/25">{t("error.keyPresented")}</span> {keyChange.presented_pubkey}
+              </p>
+              <button
+                onClick={() => onTrust(keyChange)}
+                className="mt-2 text-[12.5px] text-red-600 dark:text-red-400 hover:underline"
+              >
+                {t("error.trustNewKey")}
+              </button>
+            </div>
+          )}
+
           <div className="mt-12 pt-6 border-t border-black/[0.07] dark:border-white/[0.06] space-y-1">
             <p className="font-mono text-[11.5px] text-foreground/30 truncate">{url}</p>
             <p className="font-mono text-[11.5px] text-foreground/30 break-all">
-              {code && <span className="text-foreground/20">[{category}:{code}]</span>}
-              {code ? " " : ""}{message}
+              <span className="text-foreground/20">[{category}:{code}]</span> {message}
             </p>
           </div>
 
@@ -2032,19 +2278,183 @@ function groupHistory(entries: HistoryEntry[]): Array<{ label: string; items: Hi
   return order.map((label) => ({ label, items: map.get(label)! }))
 }
 
+interface PageMatch {
+  url: string
+  title: string
+  snippet: string
+  visit_id: number | null
+  visited_at: number | null
+  score: number
+}
+
+// Rust marks matched terms in snippets with U+E000 … U+E001.
+function renderSnippet(snippet: string): React.ReactNode[] {
+  return snippet.split("\uE000").flatMap((part, i) => {
+    if (i === 0) return [part]
+    const [hit, ...rest] = part.split("\uE001")
+    return [
+      <mark key={i} className="bg-[#ffd60a]/40 dark:bg-[#ffd60a]/25 text-inherit rounded-sm">{hit}</mark>,
+      rest.join(""),
+    ]
+  })
+}
+
+const HISTORY_CLEAR_RANGES: { label: TranslationKey; ms?: number }[] = [
+  { label: "history.rangeLastHour", ms: 3_600_000 },
+  { label: "history.rangeLastDay", ms: 86_400_000 },
+  { label: "history.rangeLastWeek", ms: 7 * 86_400_000 },
+  { label: "history.rangeLastMonth", ms: 30 * 86_400_000 },
+  { label: "history.rangeAllTime" },
+]
+
 function HistoryPage({
-  entries,
   onNavigate,
   onRemove,
-  onClearAll,
+  onClearRange,
   isMobile,
 }: {
-  entries: HistoryEntry[]
   onNavigate: (url: string) => void
   onRemove: (url: string) => void
-  onClearAll: () => void
+  onClearRange: (from?: number) => Promise<void>
   isMobile?: boolean
 }) {
+  const [entries, setEntries] = useState<HistoryEntry[]>([])
+  const [cursor, setCursor] = useState<number | null>(null)
+  const [clearRange, setClearRange] = useState(HISTORY_CLEAR_RANGES.length - 1)
+  const [search, setSearch] = useState("")
+  const [matches, setMatches] = useState<PageMatch[] | null>(null)
+  const loadingRef = useRef(false)
+
+  useEffect(() => {
+    const query = search.trim()
+    if (!query) { setMatches(null); return }
+    let stale = false
+    const timer = setTimeout(() => {
+      invoke<PageMatch[]>("search_pages", { query })
+        .then((found) => { if (!stale) setMatches(found) })
+        .catch(console.error)
+    }, 150)
+    return () => { stale = true; clearTimeout(timer) }
+  }, [search])
+
+  const loadPage = (before?: number) => {
+    if (loadingRef.current) return
+    loadingRef.current = true
+    invoke<HistoryPageResult>("query_history", { query: { before } })
+      .then((page) => {
+        const items = page.visits.map(visitToEntry)
+        setEntries((prev) => (before === undefined ? items : [...prev, ...items]))
+        setCursor(page.next_cursor ?? null)
+      })
+      .catch(console.error)
+      .finally(() => { loadingRef.current = false })
+  }
+
+  useEffect(() => { loadPage() }, []) // eslint-disable-line react-hooks/exhaustive-deps
+
+  // Older pages load as the list nears its end.
+  const handleScroll = (e: React.UIEvent<HTMLDivElement>) => {
+    const el = e.currentTarget
+    if (cursor !== null && el.scrollTop + el.clientHeight > el.scrollHeight - 400) loadPage(cursor)
+  }
+
+  const handleRemove = (url: string) => {
+    onRemove(url)
+    setEntries((prev) => prev.filter((e) => e.url !== url))
+    setMatches((prev) => prev && prev.filter((m) => m.url.split("#")[0] !== url.split("#")[0]))
+  }
+
+  const handleClear = () => {
+    const { ms } = HISTORY_CLEAR_RANGES[clearRange]
+    onClearRange(ms === undefined ? undefined : Date.now() - ms)
+      .then(() => {
+        loadingRef.current = false
+        loadPage()
+        if (search.trim()) invoke<PageMatch[]>("search_pages", { query: search.trim() }).then(setMatches)
+      })
+      .catch(console.error)
+  }
+
+  const searchInput = (
+    <div className={cn(
+      "flex items-center gap-1.5 rounded-md bg-black/[0.05] dark:bg-white/[0.07]",
+      isMobile ? "mx-5 mb-3 h-9 px-2.5" : "h-7 w-64 px-2",
+    )}>
+      <Search className="size-3.5 shrink-0 text-foreground/35" />
+      <input
+        type="search"
+        value={search}
+        onChange={(e) => setSearch(e.target.value)}
+        placeholder={t("history.searchPlaceholder")}
+        aria-label={t("history.searchPlaceholder")}
+        className={cn(
+          "flex-1 min-w-0 bg-transparent outline-none text-foreground placeholder:text-foreground/35",
+          isMobile ? "text-[15px]" : "text-[12.5px]",
+        )}
+      />
+    </div>
+  )
+
+  const matchList = matches && (
+    matches.length === 0 ? (
+      <div className="flex-1 flex items-center justify-center">
+        <p className="text-[13px] text-foreground/40">{t("history.noMatches")}</p>
+      </div>
+    ) : (
+      <div className={cn("flex-1 overflow-y-auto", isMobile && "px-4 pb-4")}>
+        {matches.map((match) => (
+          <div
+            key={match.url}
+            className={cn(
+              "group flex items-start gap-3 transition-colors",
+              isMobile
+                ? "px-4 py-3 mb-2 rounded-xl bg-white dark:bg-[#2c2c2e] active:bg-black/5 dark:active:bg-white/5"
+                : "px-6 py-2.5 border-b border-black/[0.05] dark:border-white/[0.04] hover:bg-black/[0.03] dark:hover:bg-white/[0.04]",
+            )}
+          >
+            <button className="flex-1 min-w-0 text-start" onClick={() => onNavigate(match.url)}>
+              <p className={cn("text-foreground truncate", isMobile ? "text-[14px]" : "text-[13px]")}>
+                {match.title || getDisplayHost(match.url)}
+              </p>
+              <p className="text-[12px] text-foreground/55 line-clamp-2 mt-0.5">{renderSnippet(match.snippet)}</p>
+              <p className="text-[11px] text-foreground/35 truncate mt-0.5">
+                {getDisplayHost(match.url)}
+                {match.visited_at !== null && ` · ${new Date(match.visited_at).toLocaleDateString()}`}
+              </p>
+            </button>
+            <button
+              onClick={() => handleRemove(match.url)}
+              className={cn(
+                "opacity-0 group-hover:opacity-100 transition-opacity shrink-0 mt-0.5",
+                "size-4 rounded flex items-center justify-center",
+                "text-foreground/40 hover:text-foreground hover:bg-black/8 dark:hover:bg-white/10",
+              )}
+              aria-label={t("history.removeFromHistory")}
+            >
+              <X className="size-2.5" />
+            </button>
+          </div>
+        ))}
+      </div>
+    )
+  )
+
+  const rangeSelect = (
+    <select
+      value={clearRange}
+      onChange={(e) => setClearRange(Number(e.target.value))}
+      aria-label={t("history.clearRange")}
+      className={cn(
+        "rounded-md bg-transparent text-foreground/60 outline-none cursor-pointer",
+        isMobile ? "text-[15px]" : "h-7 px-1.5 text-[12px]",
+      )}
+    >
+      {HISTORY_CLEAR_RANGES.map(({ label }, i) => (
+        <option key={label} value={i}>{t(label)}</option>
+      ))}
+    </select>
+  )
+
   const groups = groupHistory(entries)
 
   if (isMobile) {
@@ -2054,21 +2464,25 @@ function HistoryPage({
         <div className="shrink-0 px-5 pt-6 pb-4 flex items-center justify-between">
           <h1 className="text-[28px] font-bold text-foreground tracking-tight">{t("history.title")}</h1>
           {entries.length > 0 && (
-            <button
-              onClick={onClearAll}
-              className="text-[#ff3b30] dark:text-[#ff453a] text-[15px] font-medium"
-            >
-              {t("history.clearAll")}
-            </button>
+            <div className="flex items-center gap-2">
+              {rangeSelect}
+              <button
+                onClick={handleClear}
+                className="text-[#ff3b30] dark:text-[#ff453a] text-[15px] font-medium"
+              >
+                {t("history.clear")}
+              </button>
+            </div>
           )}
         </div>
+        {searchInput}
 
-        {entries.length === 0 ? (
+        {matchList ?? (entries.length === 0 ? (
           <div className="flex-1 flex items-center justify-center">
             <p className="text-[13px] text-foreground/40">{t("history.empty")}</p>
           </div>
         ) : (
-          <div className="flex-1 overflow-y-auto">
+          <div className="flex-1 overflow-y-auto" onScroll={handleScroll}>
             {groups.map(({ label: groupLabel, items }) => (
               <div key={groupLabel} className="mb-4">
                 <p className="px-5 pb-1 text-[11px] font-medium uppercase tracking-widest text-foreground/40">
@@ -2077,7 +2491,7 @@ function HistoryPage({
                 <div className="mx-4 rounded-xl overflow-hidden bg-white dark:bg-[#2c2c2e] border border-black/[0.06] dark:border-white/[0.05] divide-y divide-black/[0.06] dark:divide-white/[0.05]">
                   {items.map((entry) => (
                     <div
-                      key={entry.url}
+                      key={entry.id ?? entry.url}
                       className="group flex items-center gap-3 px-4 active:bg-black/5 dark:active:bg-white/5 transition-colors"
                     >
                       <button
@@ -2098,7 +2512,7 @@ function HistoryPage({
                         )}
                       </button>
                       <button
-                        onClick={() => onRemove(entry.url)}
+                        onClick={() => handleRemove(entry.url)}
                         className="shrink-0 size-6 rounded-full flex items-center justify-center text-foreground/30 hover:text-foreground hover:bg-black/5 dark:hover:bg-white/10 opacity-0 group-hover:opacity-100 transition-all"
                         aria-label={t("history.removeFromHistory")}
                       >
@@ -2110,7 +2524,7 @@ function HistoryPage({
               </div>
             ))}
           </div>
-        )}
+        ))}
       </div>
     )
   }
@@ -2124,26 +2538,30 @@ function HistoryPage({
         "border-b border-black/[0.07] dark:border-white/[0.05]",
       )}>
         <h2 className="text-[18px] font-semibold text-foreground tracking-tight">{t("history.title")}</h2>
-        {entries.length > 0 && (
-          <button
-            onClick={onClearAll}
-            className={cn(
-              "h-7 px-3 rounded-md text-[12px] font-medium transition-colors",
-              "bg-[#ff3b30]/10 text-[#ff3b30] hover:bg-[#ff3b30]/15",
-              "dark:text-[#ff453a] dark:bg-[#ff453a]/10 dark:hover:bg-[#ff453a]/15",
-            )}
-          >
-            {t("history.clearAll")}
-          </button>
-        )}
+        <div className="flex items-center gap-1.5">
+          {searchInput}
+          {entries.length > 0 && rangeSelect}
+          {entries.length > 0 && (
+            <button
+              onClick={handleClear}
+              className={cn(
+                "h-7 px-3 rounded-md text-[12px] font-medium transition-colors",
+                "bg-[#ff3b30]/10 text-[#ff3b30] hover:bg-[#ff3b30]/15",
+                "dark:text-[#ff453a] dark:bg-[#ff453a]/10 dark:hover:bg-[#ff453a]/15",
+              )}
+            >
+              {t("history.clear")}
+            </button>
+          )}
+        </div>
       </div>
 
-      {entries.length === 0 ? (
+      {matchList ?? (entries.length === 0 ? (
         <div className="flex-1 flex items-center justify-center">
           <p className="text-[13px] text-foreground/40">{t("history.empty")}</p>
         </div>
       ) : (
-        <div className="flex-1 overflow-y-auto">
+        <div className="flex-1 overflow-y-auto" onScroll={handleScroll}>
 
 
           <div className={cn(
@@ -2174,7 +2592,7 @@ function HistoryPage({
 
               {items.map((entry) => (
                 <div
-                  key={entry.url}
+                  key={entry.id ?? entry.url}
                   className={cn(
                     "group grid grid-cols-[1fr_260px] transition-colors",
                     "border-b border-black/[0.05] dark:border-white/[0.04]",
@@ -2203,7 +2621,7 @@ function HistoryPage({
                       </span>
                     )}
                     <button
-                      onClick={() => onRemove(entry.url)}
+                      onClick={() => handleRemove(entry.url)}
                       className={cn(
                         "opacity-0 group-hover:opacity-100 transition-opacity shrink-0",
                         "size-4 rounded flex items-center justify-center",
@@ -2220,7 +2638,7 @@ function HistoryPage({
           ))}
 
         </div>
-      )}
+      ))}
 
     </div>
   )
@@ -2319,6 +2737,7 @@ function ContextMenuOverlay({
   const shareRef = useRef<HTMLButtonElement>(null)
   const [shareRect, setShareRect] = useState<DOMRect | null>(null)
   const shareTimer = useRef<ReturnType<typeof setTimeout> | undefined>(undefined)
+  const pageDisplayUrl = useDisplayUrl(pageUrl)
 
   if (!ctx) return null
 
@@ -2344,14 +2763,20 @@ function ContextMenuOverlay({
   const closeShare = () => { shareTimer.current = setTimeout(() => setShareOpen(false), 120) }
 
   const copyShareLink = () => {
-    navigator.clipboard.writeText(prettyNwepUrl(pageUrl)).catch(() => {})
-    setLinkCopied(true)
-    setTimeout(() => { setLinkCopied(false); setCtx(null) }, 1000)
+    navigator.clipboard.writeText(pageDisplayUrl)
+      .then(() => {
+        setLinkCopied(true)
+        setTimeout(() => { setLinkCopied(false); setCtx(null) }, 1000)
+      })
+      .catch((err) => { console.error(err); setCtx(null) })
   }
   const copyProxyLink = () => {
-    navigator.clipboard.writeText(`https://proxy.usenwep.org/?addr=${prettyNwepUrl(pageUrl)}`).catch(() => {})
-    setProxyCopied(true)
-    setTimeout(() => { setProxyCopied(false); setCtx(null) }, 1000)
+    navigator.clipboard.writeText(`https://proxy.usenwep.org/?addr=${pageDisplayUrl}`)
+      .then(() => {
+        setProxyCopied(true)
+        setTimeout(() => { setProxyCopied(false); setCtx(null) }, 1000)
+      })
+      .catch((err) => { console.error(err); setCtx(null) })
   }
 
   const hasLinkSection = hasLink
@@ -3005,8 +3430,6 @@ function MobileBottomChrome({
   onShowTabs,
   onNavigate,
   onNewTab,
-  bookmarks,
-  globalHistory,
   searchBookmarks: enableSearchBookmarks,
   searchHistory: enableSearchHistory,
 }: {
@@ -3017,8 +3440,6 @@ function MobileBottomChrome({
   onShowTabs: () => void
   onNavigate: (url: string) => void
   onNewTab: () => void
-  bookmarks: BookmarkTree
-  globalHistory: HistoryEntry[]
   searchBookmarks: boolean
   searchHistory: boolean
 }) {
@@ -3029,30 +3450,7 @@ function MobileBottomChrome({
   const [draft, setDraft] = useState("")
   const inputRef = useRef<HTMLInputElement>(null)
 
-  const flatBks = useMemo(() => flatBookmarks(bookmarks), [bookmarks])
-  const suggestions = useMemo<Suggestion[]>(() => {
-    const q = draft.trim().toLowerCase()
-    if (!q) return []
-    const results: Suggestion[] = []
-    if (enableSearchBookmarks) {
-      for (const b of flatBks) {
-        if (b.url.toLowerCase().includes(q) || b.title.toLowerCase().includes(q)) {
-          results.push({ type: "bookmark", url: b.url, title: b.title })
-          if (results.length >= 5) break
-        }
-      }
-    }
-    if (enableSearchHistory) {
-      const seen = new Set(results.map((r) => r.url))
-      for (const h of globalHistory) {
-        if (!seen.has(h.url) && (h.url.toLowerCase().includes(q) || (h.title ?? "").toLowerCase().includes(q))) {
-          results.push({ type: "history", url: h.url, title: h.title })
-          if (results.length >= 8) break
-        }
-      }
-    }
-    return results
-  }, [draft, enableSearchBookmarks, enableSearchHistory, flatBks, globalHistory])
+  const { suggestions } = useSuggestions(editing ? draft : "", enableSearchBookmarks, enableSearchHistory)
 
   const startEditing = () => {
     setDraft(isEmpty ? "" : url)
@@ -3063,7 +3461,7 @@ function MobileBottomChrome({
     setEditing(false)
     setDraft("")
     if (target) {
-      onNavigate(target.startsWith("about:") ? target : normalizeNwepUrl(target))
+      normalizeAddress(target).then(onNavigate)
     }
   }
   const cancelEdit = () => { setEditing(false); setDraft("") }
@@ -3166,7 +3564,7 @@ function MobileBottomChrome({
                   onClick={() => commitEdit(s.url)}
                   className="flex items-center gap-3 w-full px-4 py-3 text-start active:bg-black/5 dark:active:bg-white/5"
                 >
-                  {s.type === "bookmark"
+                  {s.kind === "bookmark"
                     ? <Bookmark className="size-4 text-[#0a84ff] shrink-0" />
                     : <Clock className="size-4 text-foreground/40 shrink-0" />}
                   <div className="min-w-0 flex-1">
@@ -3537,16 +3935,16 @@ function MobileBookmarksSheet({
 
   const saveEdit = () => {
     if (!editingBookmark) return
-    onTreeChange(updateNode(bookmarks, editingBookmark.id, {
+    updateNode(editingBookmark.id, {
       title: editTitle.trim() || getDisplayHost(editUrl),
       url: editUrl.trim(),
-    }))
+    }).then(onTreeChange).catch(console.error)
     setEditingBookmark(null)
   }
 
   const deleteBookmark = () => {
     if (!editingBookmark) return
-    onTreeChange(removeNode(bookmarks, editingBookmark.id))
+    removeNode(editingBookmark.id).then(onTreeChange).catch(console.error)
     setEditingBookmark(null)
   }
 
@@ -3780,11 +4178,43 @@ function MobileBookmarksSheet({
 
 
 let nextId = 2
+let nextRequestId = 1
 
 function makeTab(url = "about:newtab"): Tab {
   return { id: String(nextId++), title: "", url, history: [], historyIndex: -1 }
 }
 
+function decodeBase64(b64: string): Uint8Array {
+  const bin = atob(b64)
+  const bytes = new Uint8Array(bin.length)
+  for (let i = 0; i < bin.length; i++) bytes[i] = bin.charCodeAt(i)
+  return bytes
+}
+
+function escapeHtml(s: string): string {
+  return s.replace(/[&<>"]/g, (c) => ({ "&": "&amp;", "<": "&lt;", ">": "&gt;", '"': "&quot;" })[c]!)
+}
+
+function fileNameFromUrl(url: string): string {
+  const path = url.split(/[?#]/)[0]
+  return decodeURIComponent(path.slice(path.lastIndexOf("/") + 1)) || "download"
+}
+
+// Returns null when the body can only be downloaded.
+function bodyToDocument(result: NwepResult): string | null {
+  const mime = (result.content_type ?? "").split(";")[0].trim().toLowerCase()
+  if (result.text != null) {
+    return mime === "text/html" || mime === "application/xhtml+xml"
+      ? result.text
+      : `<pre style="white-space:pre-wrap;word-break:break-word">${escapeHtml(result.text)}</pre>`
+  }
+  if (mime.startsWith("image/")) {
+    return `<body style="margin:0;min-height:100vh;display:flex;align-items:center;justify-content:center">`
+      + `<img src="data:${escapeHtml(mime)};base64,${result.body_base64 ?? ""}" style="max-width:100%"></body>`
+  }
+  return null
+}
+
 function extractPageTitle(html: string, fallback: string): string {
   const m = html.match(/<title[^>]*>([^<]*)<\/title>/i)
   return m?.[1]?.trim() || fallback
@@ -3794,7 +4224,7 @@ const ZOOM_STEP = 0.1
 const ZOOM_MIN = 0.25
 const ZOOM_MAX = 3.0
 
-export default function App() {
+export default function App({ initialSettings }: { initialSettings: BrowserSettings }) {
   const [{ tabs, activeTabId }, setTabsState] = useState({
     tabs: [{ id: "1", title: "", url: "about:newtab", history: [], historyIndex: -1 }] as Tab[],
     activeTabId: "1",
@@ -3809,13 +4239,13 @@ export default function App() {
     [],
   )
   const [isLoading, setIsLoading] = useState(false)
-  const [settings, setSettings] = useState<BrowserSettings>(loadSettings)
-  const [zoom, setZoom] = useState(() => loadSettings().defaultZoom)
+  const [settings, setSettings] = useState(initialSettings)
+  const [zoom, setZoom] = useState(initialSettings.defaultZoom)
   const [findOpen, setFindOpen] = useState(false)
-  const [bookmarks, setBookmarks] = useState<BookmarkTree>(loadBookmarks)
+  const [bookmarks, setBookmarks] = useState<BookmarkTree>([])
   const [showOnboarding, setShowOnboarding] = useState(() => !isOnboardingComplete())
   const [tourTip, setTourTip] = useState<null | "address-bar" | "swipe-up" | "bookmarks" | "bookmarks-opened" | "more-options" | "more-options-opened" | "new-tab" | "swipe-to-close">(null)
-const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHistory)
+const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>([])
   const [tabSwitcherOpen, setTabSwitcherOpen] = useState(false)
   const [optionsSheetOpen, setOptionsSheetOpen] = useState(false)
   const [bookmarksSheetOpen, setBookmarksSheetOpen] = useState(false)
@@ -3824,6 +4254,11 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
 
   const tourTipRef = useRef(tourTip)
   useEffect(() => { tourTipRef.current = tourTip }, [tourTip])
+  useEffect(() => {
+    loadBookmarks().then(setBookmarks).catch(console.error)
+    loadGlobalHistory().then(setGlobalHistory).catch(console.error)
+  }, [])
+  useEffect(() => subscribeSettings(setSettings), [])
 
   const isMobile = useMemo(() => {
     if (settings.developerForceMobileUi === "mobile") return true
@@ -3893,54 +4328,106 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
   const zoomOut = () => setZoom((z) => Math.max(ZOOM_MIN, parseFloat((z - ZOOM_STEP).toFixed(2))))
   const zoomReset = () => setZoom(1.0)
 
-  const fetchAndLoad = async (url: string, tabId: string) => {
+  const inflightRequests = useRef(new Map<string, string>())
+
+  const cancelInflight = (tabId: string) => {
+    const requestId = inflightRequests.current.get(tabId)
+    if (!requestId) return
+    inflightRequests.current.delete(tabId)
+    invoke("nwep_cancel", { requestId }).catch(() => {})
+    setIsLoading(false)
+  }
+
+  const fetchAndLoad = async (url: string, tabId: string, transition: Transition = "link") => {
+    cancelInflight(tabId)
+    const requestId = `${tabId}:${nextRequestId++}`
+    inflightRequests.current.set(tabId, requestId)
+    const isCurrent = () => inflightRequests.current.get(tabId) === requestId
     setIsLoading(true)
     try {
-      const result = await invoke<NwepResult>("nwep_fetch", { url })
+      const liveLog: LogStep[] = []
+      const onProgress = new Channel<FetchEvent>()
+      onProgress.onmessage = (msg) => {
+        if (msg.event !== "step" || !isCurrent()) return
+        liveLog.push(msg.data)
+        setTabs((prev) => prev.map((tab) => tab.id === tabId
+          ? { ...tab, connectionInfo: { ...tab.connectionInfo, log: [...liveLog] } }
+          : tab))
+      }
+      const result = await invoke<NwepResult>("nwep_fetch", { url, requestId, onProgress })
+      if (result.cancelled || !isCurrent()) return
 
       const connectionInfo: ConnectionInfo | undefined = result.log.length > 0
         ? {
             clientNodeId: result.connection?.client_node_id,
             serverNodeId: result.connection?.server_node_id,
             serverPubkey: result.connection?.server_pubkey,
+            ttfbMs: result.ttfb_ms ?? undefined,
+            totalMs: result.total_ms,
             log: result.log,
           }
         : undefined
 
       if (!result.ok) {
         setTabs((prev) => prev.map((tab) => tab.id === tabId
-          ? { ...tab, error: result.error ?? "Unknown error", connectionInfo }
+          ? { ...tab, error: result.error ?? { category: "internal", code: 0, message: "Unknown error", retryable: false }, keyChange: result.key_change ?? undefined, connectionInfo }
           : tab))
         return
       }
 
-      const title = extractPageTitle(result.body!, getDisplayHost(url))
+      // After redirects the page lives at a different address than the one requested.
+      const finalUrl = result.url ?? url
+      const body = bodyToDocument(result)
+      const download: PendingDownload | undefined = body == null
+        ? {
+            name: fileNameFromUrl(finalUrl),
+            contentType: result.content_type ?? "application/octet-stream",
+            bodyBase64: result.body_base64 ?? "",
+          }
+        : undefined
+      const title = download?.name ?? extractPageTitle(body ?? "", getDisplayHost(finalUrl))
       setTabs((prev) => prev.map((tab) => tab.id === tabId
-        ? { ...tab, content: result.body!, title, connectionInfo }
+        ? {
+            ...tab,
+            url: finalUrl,
+            content: body ?? undefined,
+            download,
+            title,
+            stale: result.stale ?? undefined,
+            connectionInfo,
+            history: tab.history.map((h, i) => (i === tab.historyIndex ? finalUrl : h)),
+          }
         : tab))
       if (settings.historyEnabled) {
-        setGlobalHistory((prev) => {
-          const next = [{ url, title, timestamp: Date.now() }, ...prev.filter((e) => e.url !== url)].slice(0, MAX_HISTORY)
-          saveGlobalHistory(next)
-          return next
-        })
+        invoke<number>("record_visit", { visit: { url: finalUrl, title, transition, tab_id: tabId } })
+          .then((id) => setGlobalHistory((prev) => [
+            { id, url: finalUrl, title, timestamp: Date.now() },
+            ...prev.filter((e) => e.url !== finalUrl),
+          ].slice(0, RECENT_HISTORY_LIMIT)))
+          .catch(console.error)
       }
     } catch (err) {
-      setTabs((prev) => prev.map((tab) => tab.id === tabId ? { ...tab, error: String(err) } : tab))
+      if (!isCurrent()) return
+      const error: NwepError = { category: "internal", code: 0, message: String(err), retryable: false }
+      setTabs((prev) => prev.map((tab) => tab.id === tabId ? { ...tab, error } : tab))
     } finally {
-      setIsLoading(false)
+      if (isCurrent()) {
+        inflightRequests.current.delete(tabId)
+        setIsLoading(false)
+      }
     }
   }
 
-  const navigate = (url: string) => {
+  const navigate = (url: string, transition: Transition = "typed") => {
     const tabId = activeTabId
     setFindOpen(false)
     setTabs((prev) => prev.map((tab) => {
       if (tab.id !== tabId) return tab
       const newHistory = [...tab.history.slice(0, tab.historyIndex + 1), url]
-      return { ...tab, url, title: url, content: undefined, error: undefined, connectionInfo: undefined, history: newHistory, historyIndex: newHistory.length - 1 }
+      return { ...tab, url, title: url, content: undefined, error: undefined, keyChange: undefined, stale: undefined, download: undefined, connectionInfo: undefined, history: newHistory, historyIndex: newHistory.length - 1 }
     }))
-    if (!url.startsWith("about:")) fetchAndLoad(url, tabId)
+    if (!url.startsWith("about:")) fetchAndLoad(url, tabId, transition)
+    else cancelInflight(tabId)
   }
 
   const goBack = () => {
@@ -3950,9 +4437,10 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
     const url = tab.history[newIdx]
     setFindOpen(false)
     setTabs((prev) => prev.map((tab) =>
-      tab.id === activeTabId ? { ...tab, url, title: url, content: undefined, error: undefined, connectionInfo: undefined, historyIndex: newIdx } : tab
+      tab.id === activeTabId ? { ...tab, url, title: url, content: undefined, error: undefined, keyChange: undefined, stale: undefined, download: undefined, connectionInfo: undefined, historyIndex: newIdx } : tab
     ))
-    if (!url.startsWith("about:")) fetchAndLoad(url, activeTabId)
+    if (!url.startsWith("about:")) fetchAndLoad(url, activeTabId, "back_forward")
+    else cancelInflight(activeTabId)
   }
 
   const goForward = () => {
@@ -3962,9 +4450,10 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
     const url = tab.history[newIdx]
     setFindOpen(false)
     setTabs((prev) => prev.map((tab) =>
-      tab.id === activeTabId ? { ...tab, url, title: url, content: undefined, error: undefined, connectionInfo: undefined, historyIndex: newIdx } : tab
+      tab.id === activeTabId ? { ...tab, url, title: url, content: undefined, error: undefined, keyChange: undefined, stale: undefined, download: undefined, connectionInfo: undefined, historyIndex: newIdx } : tab
     ))
-    if (!url.startsWith("about:")) fetchAndLoad(url, activeTabId)
+    if (!url.startsWith("about:")) fetchAndLoad(url, activeTabId, "back_forward")
+    else cancelInflight(activeTabId)
   }
 
   const goToHistory = (index: number) => {
@@ -3973,86 +4462,68 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
     const url = tab.history[index]
     setFindOpen(false)
     setTabs((prev) => prev.map((tab) =>
-      tab.id === activeTabId ? { ...tab, url, title: url, content: undefined, error: undefined, historyIndex: index } : tab
+      tab.id === activeTabId ? { ...tab, url, title: url, content: undefined, error: undefined, keyChange: undefined, stale: undefined, download: undefined, historyIndex: index } : tab
     ))
-    if (!url.startsWith("about:")) fetchAndLoad(url, activeTabId)
+    if (!url.startsWith("about:")) fetchAndLoad(url, activeTabId, "back_forward")
+    else cancelInflight(activeTabId)
   }
 
-  const persistBookmarks = (tree: BookmarkTree) => {
-    saveBookmarks(tree)
-    setBookmarks(tree)
+  const applyBookmarks = (change: Promise<BookmarkTree>) => {
+    change.then(setBookmarks).catch(console.error)
   }
 
   const addBookmark = (url: string, title: string) => {
     if (isBookmarked(bookmarks, url)) return
-    persistBookmarks(
-      addNode(bookmarks, null, {
-        id: genId(),
-        type: "bookmark",
-        url,
-        title: title || getDisplayHost(url),
-      })
-    )
+    applyBookmarks(addNode(null, { type: "bookmark", url, title: title || getDisplayHost(url) }))
   }
 
   const removeBookmarkById = (id: string) => {
-    persistBookmarks(removeNode(bookmarks, id))
+    applyBookmarks(removeNode(id))
   }
 
   const addAllBookmarks = () => {
-    let tree = bookmarks
-    for (const tab of tabs) {
-      if (!tab.url.startsWith("about:") && !isBookmarked(tree, tab.url)) {
-        tree = addNode(tree, null, {
-          id: genId(),
-          type: "bookmark",
-          url: tab.url,
-          title: tab.title || getDisplayHost(tab.url),
-        })
+    applyBookmarks((async () => {
+      let tree = bookmarks
+      for (const tab of tabs) {
+        if (!tab.url.startsWith("about:") && !isBookmarked(tree, tab.url)) {
+          tree = await addNode(null, {
+            type: "bookmark",
+            url: tab.url,
+            title: tab.title || getDisplayHost(tab.url),
+          })
+        }
       }
-    }
-    persistBookmarks(tree)
+      return tree
+    })())
   }
 
   const toggleBookmarkForTab = useCallback(() => {
     const tab = tabs.find((tab) => tab.id === activeTabId)
     if (!tab || tab.url.startsWith("about:")) return
-    setBookmarks((prev) => {
-      const next = isBookmarked(prev, tab.url)
-        ? removeByUrl(prev, tab.url)
-        : addNode(prev, null, {
-            id: genId(),
-            type: "bookmark",
-            url: tab.url,
-            title: tab.title || getDisplayHost(tab.url),
-          })
-      saveBookmarks(next)
-      return next
-    })
-  }, [tabs, activeTabId])
+    applyBookmarks(isBookmarked(bookmarks, tab.url)
+      ? removeByUrl(tab.url)
+      : addNode(null, { type: "bookmark", url: tab.url, title: tab.title || getDisplayHost(tab.url) }))
+  }, [tabs, activeTabId, bookmarks]) // eslint-disable-line react-hooks/exhaustive-deps
 
   const persistSettings = (s: BrowserSettings) => {
-    saveSettings(s)
     setSettings(s)
+    updateSettings(s).then(setSettings).catch(console.error)
   }
 
   const clearAllHistory = () => {
     setTabs((prev) => prev.map((tab) => ({ ...tab, history: tab.url.startsWith("about:") ? [] : [tab.url], historyIndex: tab.url.startsWith("about:") ? -1 : 0 })))
     setGlobalHistory([])
-    saveGlobalHistory([])
+    invoke("delete_history_range", {}).catch(console.error)
   }
 
-  const clearGlobalHistory = () => {
-    setGlobalHistory([])
-    saveGlobalHistory([])
+  const clearHistoryRange = async (from?: number) => {
+    await invoke("delete_history_range", { from })
+    setGlobalHistory(await loadGlobalHistory())
   }
 
   const removeHistoryEntry = (url: string) => {
-    setGlobalHistory((prev) => {
-      const next = prev.filter((e) => e.url !== url)
-      saveGlobalHistory(next)
-      return next
-    })
+    setGlobalHistory((prev) => prev.filter((e) => e.url !== url))
+    invoke("delete_history_url", { url }).catch(console.error)
   }
 
   const reorderTabs = (reordered: Tab[]) => setTabsState((s) => ({ ...s, tabs: reordered }))
@@ -4064,7 +4535,7 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
     const tab = makeTab(url)
     setTabsState((s) => ({ tabs: [...s.tabs, tab], activeTabId: tab.id }))
     setFindOpen(false)
-    if (!url.startsWith("about:")) fetchAndLoad(url, tab.id)
+    if (!url.startsWith("about:")) fetchAndLoad(url, tab.id, "start_page")
   }
 
   const addTabBackground = () => {
@@ -4073,7 +4544,7 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
       : "about:newtab"
     const tab = makeTab(url)
     setTabsState((s) => ({ tabs: [...s.tabs, tab], activeTabId: s.activeTabId }))
-    if (!url.startsWith("about:")) fetchAndLoad(url, tab.id)
+    if (!url.startsWith("about:")) fetchAndLoad(url, tab.id, "start_page")
   }
 
   const openInNewTab = (url: string) => {
@@ -4083,6 +4554,19 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
     fetchAndLoad(url, tab.id)
   }
 
+  const followLink = (href: string, newTab: boolean) => {
+    invoke<string>("resolve_url", { base: activeTab.url, href })
+      .then((target) => {
+        if (!target.startsWith("web://")) {
+          if (/^(https?|mailto):/i.test(target)) openUrl(target).catch(console.error)
+          return
+        }
+        if (newTab) openInNewTab(target)
+        else navigate(target, "link")
+      })
+      .catch(console.error)
+  }
+
   const savePage = async () => {
     const content = activeTab.content
     if (!content) return
@@ -4097,6 +4581,7 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
   }
 
   const closeTab = (id: string) => {
+    cancelInflight(id)
     setTabsState((s) => {
       const idx = s.tabs.findIndex((tab) => tab.id === id)
       const next = s.tabs.filter((tab) => tab.id !== id)
@@ -4132,6 +4617,7 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
           onChange={persistSettings}
           onClearHistory={clearAllHistory}
           onOpenHistory={() => navigate("about:history")}
+          onBookmarksChange={setBookmarks}
           onResetOnboarding={() => { resetOnboarding(); setShowOnboarding(true) }}
           isMobile={isMobile}
           currentVersion={currentVersion}
@@ -4140,10 +4626,9 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
         />
       ) : activeTab.url === "about:history" ? (
         <HistoryPage
-          entries={globalHistory}
           onNavigate={navigate}
           onRemove={removeHistoryEntry}
-          onClearAll={clearGlobalHistory}
+          onClearRange={clearHistoryRange}
           isMobile={isMobile}
         />
       ) : activeTab.url === "about:newtab" && isMobile ? (
@@ -4152,10 +4637,27 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
         <ErrorPage
           error={activeTab.error}
           url={activeTab.url}
-          onRetry={() => navigate(activeTab.url)}
+          keyChange={activeTab.keyChange}
+          onRetry={() => navigate(activeTab.url, "reload")}
+          onTrust={(change) => {
+            invoke("trust_known_node", { nodeId: change.node_id, pubkey: change.presented_pubkey })
+              .then(() => navigate(activeTab.url, "reload"))
+              .catch(console.error)
+          }}
         />
+      ) : activeTab.download ? (
+        <DownloadPage key={activeTab.url} download={activeTab.download} />
       ) : activeTab.content != null ? (
-        <ContentFrame ref={contentFrameRef} url={activeTab.url} content={activeTab.content} zoom={zoom} />
+        <>
+          {activeTab.stale && <StaleBanner stale={activeTab.stale} />}
+          <ContentFrame
+            ref={contentFrameRef}
+            url={activeTab.url}
+            content={activeTab.content}
+            zoom={zoom}
+            onFollowLink={followLink}
+          />
+        </>
       ) : null}
       {findOpen && activeTab.url !== "about:settings" && (
         <FindBar
@@ -4185,7 +4687,7 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
         pageUrl={activeTab.url}
         onBack={goBack}
         onForward={goForward}
-        onReload={() => navigate(activeTab.url)}
+        onReload={() => navigate(activeTab.url, "reload")}
         onSavePage={savePage}
       />
 
@@ -4202,7 +4704,7 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
 
 
           <PullToRefresh
-            onRefresh={() => navigate(activeTab.url)}
+            onRefresh={() => navigate(activeTab.url, "reload")}
             enabled={!activeTab.url.startsWith("about:")}
           >
             {contentArea}
@@ -4240,8 +4742,6 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
             onShowTabs={() => { setTabSwitcherOpen(true); if (tourTipRef.current === "swipe-up") setTourTip("bookmarks") }}
             onNavigate={navigate}
             onNewTab={() => { addTab(); if (tourTip === "address-bar") setTourTip("swipe-up") }}
-            bookmarks={bookmarks}
-            globalHistory={globalHistory}
             searchBookmarks={settings.searchBookmarks}
             searchHistory={settings.searchHistory}
           />
@@ -4257,7 +4757,7 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
               onOpenBookmarks={() => { setBookmarksSheetOpen(true); if (tourTip === "bookmarks") setTourTip("bookmarks-opened") }}
               onMenu={() => { setOptionsSheetOpen(true); if (tourTipRef.current === "more-options") setTourTip("more-options-opened") }}
               onDone={() => { setTabSwitcherOpen(false); if (tourTipRef.current === "more-options" || tourTipRef.current === "new-tab" || tourTipRef.current === "swipe-to-close") setTourTip(null) }}
-              onReload={() => navigate(activeTab.url)}
+              onReload={() => navigate(activeTab.url, "reload")}
               showSwipeToCloseTip={tourTip === "swipe-to-close"}
               onSwipeToCloseTipDismiss={() => setTourTip(null)}
             />
@@ -4274,7 +4774,7 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
               isLoading={isLoading}
               isCurrentlyBookmarked={!!flatBookmarks(bookmarks).find((b) => b.url === activeTab.url)}
               onClose={() => { setOptionsSheetOpen(false); if (tourTipRef.current === "more-options-opened") setTourTip("new-tab") }}
-              onReload={isLoading ? () => setIsLoading(false) : () => navigate(activeTab.url)}
+              onReload={isLoading ? () => setIsLoading(false) : () => navigate(activeTab.url, "reload")}
               onBack={() => { goBack(); setOptionsSheetOpen(false); setTabSwitcherOpen(false); if (tourTipRef.current === "more-options-opened") setTourTip("new-tab") }}
               onForward={() => { goForward(); setOptionsSheetOpen(false); setTabSwitcherOpen(false); if (tourTipRef.current === "more-options-opened") setTourTip("new-tab") }}
               onFind={() => { setFindOpen(true); setOptionsSheetOpen(false); setTabSwitcherOpen(false); if (tourTipRef.current === "more-options-opened") setTourTip("new-tab") }}
@@ -4291,7 +4791,7 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
             bookmarks={bookmarks}
             globalHistory={globalHistory}
             onNavigate={(url) => { navigate(url); setBookmarksSheetOpen(false) }}
-            onTreeChange={persistBookmarks}
+            onTreeChange={setBookmarks}
           />
 
         </>
@@ -4319,7 +4819,7 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
             bookmarks={bookmarks}
             onBack={goBack}
             onForward={goForward}
-            onReload={() => navigate(activeTab.url)}
+            onReload={() => navigate(activeTab.url, "reload")}
             onStop={() => setIsLoading(false)}
             onNavigate={navigate}
             onZoomIn={zoomIn}
@@ -4331,10 +4831,9 @@ const [globalHistory, setGlobalHistory] = useState<HistoryEntry[]>(loadGlobalHis
             onAddBookmark={addBookmark}
             onRemoveBookmark={removeBookmarkById}
             onAddAllBookmarks={addAllBookmarks}
-            onTreeChange={persistBookmarks}
+            onTreeChange={setBookmarks}
             onOpenSettings={() => navigate("about:settings")}
             onOpenHistory={() => navigate("about:history")}
-            globalHistory={globalHistory}
             searchBookmarks={settings.searchBookmarks}
             searchHistory={settings.searchHistory}
             connectionInfo={activeTab.connectionInfo}
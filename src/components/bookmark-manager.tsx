@@ -22,13 +22,13 @@ import { cn } from "@/lib/utils"
 import { t } from "@/lib/i18n"
 import {
   BookmarkNode,
+  BookmarkPatch,
   BookmarkTree,
   addNode,
   updateNode,
   removeNode,
   getNodeAtPath,
   getFolderTitle,
-  genId,
 } from "@/lib/bookmarks"
 
 interface BookmarkManagerProps {
@@ -107,31 +107,30 @@ export function BookmarkManager({
       setEditingId(null)
       return
     }
-    const patch: Partial<BookmarkNode> = { title: editTitle.trim() }
+    const patch: BookmarkPatch = { title: editTitle.trim() }
     if (editUrl.trim()) patch.url = editUrl.trim()
-    onTreeChange(updateNode(tree, editingId, patch))
+    updateNode(editingId, patch).then(onTreeChange).catch(console.error)
     setEditingId(null)
   }
 
   const cancelEdit = () => setEditingId(null)
 
   const handleDelete = (id: string) => {
-    onTreeChange(removeNode(tree, id))
+    removeNode(id).then(onTreeChange).catch(console.error)
     if (editingId === id) setEditingId(null)
   }
 
   const handleAdd = () => {
     if (!addTitle.trim()) return
-    const node: BookmarkNode =
+    const node =
       addMode === "folder"
-        ? { id: genId(), type: "folder", title: addTitle.trim(), children: [] }
+        ? { type: "folder" as const, title: addTitle.trim() }
         : {
-            id: genId(),
-            type: "bookmark",
+            type: "bookmark" as const,
             title: addTitle.trim(),
             url: addUrl.trim() || undefined,
           }
-    onTreeChange(addNode(tree, parentId, node))
+    addNode(parentId, node).then(onTreeChange).catch(console.error)
     setAddTitle("")
     setAddUrl("")
     setAddMode("none")
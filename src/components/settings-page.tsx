@@ -1,9 +1,12 @@
 import { useState, useEffect } from "react"
 import { Globe, Palette, ShieldCheck, Code2, ChevronLeft, ChevronRight, Info, Download } from "lucide-react"
 import { useTheme } from "next-themes"
+import { invoke } from "@tauri-apps/api/core"
 import { cn } from "@/lib/utils"
 import { t, TranslationKey } from "@/lib/i18n"
 import type { BrowserSettings } from "@/lib/settings"
+import { type BookmarkTree, importBookmarks, exportBookmarks } from "@/lib/bookmarks"
+import { open as openDialog, save as saveDialog } from "@tauri-apps/plugin-dialog"
 
 
 type Section = "general" | "appearance" | "privacy" | "developers" | "about"
@@ -190,13 +193,125 @@ const LOCALES = [
   { id: "yi",    label: "ייִדיש"            },
 ]
 
+const NETWORK_ROWS: {
+  key: "connectTimeoutMs" | "requestTimeoutMs" | "retryCount" | "retryBackoffMs" | "maxConcurrentRequests"
+  label: TranslationKey
+  description: TranslationKey
+  steps: number[]
+  format: (value: number) => string
+}[] = [
+  {
+    key: "connectTimeoutMs",
+    label: "settings.connectTimeout",
+    description: "settings.connectTimeoutDesc",
+    steps: [5000, 10000, 20000, 30000, 60000],
+    format: (ms) => t("settings.secondsValue", { n: ms / 1000 }),
+  },
+  {
+    key: "requestTimeoutMs",
+    label: "settings.requestTimeout",
+    description: "settings.requestTimeoutDesc",
+    steps: [10000, 30000, 60000, 120000],
+    format: (ms) => t("settings.secondsValue", { n: ms / 1000 }),
+  },
+  {
+    key: "retryCount",
+    label: "settings.retries",
+    description: "settings.retriesDesc",
+    steps: [0, 1, 2, 3, 5],
+    format: (n) => String(n),
+  },
+  {
+    key: "retryBackoffMs",
+    label: "settings.retryBackoff",
+    description: "settings.retryBackoffDesc",
+    steps: [250, 500, 1000, 2000],
+    format: (ms) => t("settings.millisecondsValue", { n: ms }),
+  },
+  {
+    key: "maxConcurrentRequests",
+    label: "settings.maxConcurrent",
+    description: "settings.maxConcurrentDesc",
+    steps: [2, 4, 6, 8, 12, 16],
+    format: (n) => String(n),
+  },
+]
+
+const BOOKMARK_FILE_FILTERS = [{ name: "HTML", extensions: ["html", "htm"] }]
+
+function BookmarksGroup({
+  onBookmarksChange,
+  isMobile,
+}: {
+  onBookmarksChange: (tree: BookmarkTree) => void
+  isMobile?: boolean
+}) {
+  const [status, setStatus] = useState<null | "imported" | "exported" | "failed">(null)
+
+  const flash = (next: "imported" | "exported" | "failed") => {
+    setStatus(next)
+    setTimeout(() => setStatus(null), 2500)
+  }
+
+  const handleImport = async () => {
+    const path = await openDialog({
+      title: t("settings.importBookmarks"),
+      multiple: false,
+      directory: false,
+      filters: BOOKMARK_FILE_FILTERS,
+    })
+    if (typeof path !== "string") return
+    importBookmarks(path)
+      .then((tree) => { onBookmarksChange(tree); flash("imported") })
+      .catch((e) => { console.error(e); flash("failed") })
+  }
+
+  const handleExport = async () => {
+    const path = await saveDialog({
+      title: t("settings.exportBookmarks"),
+      defaultPath: "bookmarks.html",
+      filters: BOOKMARK_FILE_FILTERS,
+    })
+    if (!path) return
+    exportBookmarks(path)
+      .then(() => flash("exported"))
+      .catch((e) => { console.error(e); flash("failed") })
+  }
+
+  const buttonCls = cn(
+    "rounded-md font-medium transition-colors",
+    "bg-black/[0.05] dark:bg-white/[0.07] hover:bg-black/[0.09] dark:hover:bg-white/[0.11]",
+    "text-foreground/70 hover:text-foreground",
+    isMobile ? "h-8 px-4 text-[14px]" : "h-7 px-3 text-[12px]",
+  )
+
+  const description =
+    status === "imported" ? t("settings.bookmarksImported")
+    : status === "exported" ? t("settings.bookmarksExported")
+    : status === "failed" ? t("settings.bookmarksTransferFailed")
+    : t("settings.bookmarksTransferDesc")
+
+  return (
+    <SettingsGroup label={t("settings.bookmarks")} isMobile={isMobile}>
+      <SettingsRow label={t("settings.bookmarksTransfer")} description={description} isMobile={isMobile}>
+        <div className="flex items-center gap-1.5">
+          <button onClick={handleImport} className={buttonCls}>{t("settings.importBtn")}</button>
+          <button onClick={handleExport} className={buttonCls}>{t("settings.exportBtn")}</button>
+        </div>
+      </SettingsRow>
+    </SettingsGroup>
+  )
+}
+
 function GeneralSection({
   settings,
   update,
+  onBookmarksChange,
   isMobile,
 }: {
   settings: BrowserSettings
   update: <K extends keyof BrowserSettings>(key: K, val: BrowserSettings[K]) => void
+  onBookmarksChange: (tree: BookmarkTree) => void
   isMobile?: boolean
 }) {
   const homepageActive = settings.newTabAction === "homepage"
@@ -269,6 +384,31 @@ function GeneralSection({
           <Toggle value={settings.searchHistory} onChange={(v) => update("searchHistory", v)} />
         </SettingsRow>
       </SettingsGroup>
+
+      <SettingsGroup label={t("settings.network")} isMobile={isMobile}>
+        <SettingsRow
+          label={t("settings.workOffline")}
+          description={t("settings.workOfflineDesc")}
+          isMobile={isMobile}
+        >
+          <Toggle value={settings.offlineMode} onChange={(v) => update("offlineMode", v)} />
+        </SettingsRow>
+        {NETWORK_ROWS.map(({ key, label, description, steps, format }) => (
+          <SettingsRow key={key} label={t(label)} description={t(description)} isMobile={isMobile} stack={isMobile}>
+            <select
+              value={settings[key]}
+              onChange={(e) => update(key, Number(e.target.value))}
+              className={cn(selectCls, isMobile && "w-full h-9 text-[15px]")}
+            >
+              {steps.map((value) => (
+                <option key={value} value={value}>{format(value)}</option>
+              ))}
+            </select>
+          </SettingsRow>
+        ))}
+      </SettingsGroup>
+
+      <BookmarksGroup onBookmarksChange={onBookmarksChange} isMobile={isMobile} />
     </>
   )
 }
@@ -363,20 +503,34 @@ function AppearanceSection({
 }
 
 
+const RETENTION_STEPS = [30, 90, 180, 365, 0]
+
 function PrivacySection({
   historyEnabled,
   onToggleHistory,
+  retentionDays,
+  onRetentionChange,
   onClearHistory,
   onOpenHistory,
   isMobile,
 }: {
   historyEnabled: boolean
   onToggleHistory: (v: boolean) => void
+  retentionDays: number
+  onRetentionChange: (days: number) => void
   onClearHistory: () => void
   onOpenHistory: () => void
   isMobile?: boolean
 }) {
   const [justCleared, setJustCleared] = useState(false)
+  const [cacheBytes, setCacheBytes] = useState<number | null>(null)
+  const [cacheCleared, setCacheCleared] = useState(false)
+
+  useEffect(() => {
+    invoke<{ total_bytes: number; saved_bytes: number }>("cache_info")
+      .then((info) => setCacheBytes(info.total_bytes + info.saved_bytes))
+      .catch(() => {})
+  }, [])
 
   const handleClear = () => {
     onClearHistory()
@@ -384,6 +538,16 @@ function PrivacySection({
     setTimeout(() => setJustCleared(false), 2500)
   }
 
+  const handleClearCache = () => {
+    invoke("clear_cache")
+      .then(() => {
+        setCacheBytes(0)
+        setCacheCleared(true)
+        setTimeout(() => setCacheCleared(false), 2500)
+      })
+      .catch(console.error)
+  }
+
   return (
     <SettingsGroup label={t("settings.browsingData")} isMobile={isMobile}>
       <SettingsRow
@@ -393,6 +557,24 @@ function PrivacySection({
       >
         <Toggle value={historyEnabled} onChange={onToggleHistory} />
       </SettingsRow>
+      <SettingsRow
+        label={t("settings.keepHistory")}
+        description={t("settings.keepHistoryDesc")}
+        isMobile={isMobile}
+        stack={isMobile}
+      >
+        <select
+          value={retentionDays}
+          onChange={(e) => onRetentionChange(Number(e.target.value))}
+          className={cn(selectCls, isMobile && "w-full h-9 text-[15px]")}
+        >
+          {RETENTION_STEPS.map((days) => (
+            <option key={days} value={days}>
+              {days === 0 ? t("settings.keepForever") : t("settings.keepDays", { n: days })}
+            </option>
+          ))}
+        </select>
+      </SettingsRow>
       <SettingsRow
         label={t("settings.browsingHistory")}
         description={t("settings.browsingHistoryDesc")}
@@ -425,6 +607,27 @@ function PrivacySection({
           </button>
         </div>
       </SettingsRow>
+      <SettingsRow
+        label={t("settings.cachedPages")}
+        description={cacheBytes == null
+          ? t("settings.cachedPagesDesc")
+          : t("settings.cachedPagesSize", { size: (cacheBytes / (1024 * 1024)).toFixed(1) })}
+        isMobile={isMobile}
+      >
+        <button
+          onClick={handleClearCache}
+          disabled={cacheCleared}
+          className={cn(
+            "rounded-md font-medium transition-colors",
+            isMobile ? "h-8 px-4 text-[14px]" : "h-7 px-3 text-[12px]",
+            cacheCleared
+              ? "bg-green-500/10 text-green-600 dark:text-green-400"
+              : "bg-[#ff3b30]/10 text-[#ff3b30] hover:bg-[#ff3b30]/15 dark:text-[#ff453a] dark:bg-[#ff453a]/10 dark:hover:bg-[#ff453a]/15",
+          )}
+        >
+          {cacheCleared ? t("settings.clearedBtn") : t("settings.clearBtn")}
+        </button>
+      </SettingsRow>
     </SettingsGroup>
   )
 }
@@ -561,6 +764,7 @@ function SectionContent({
   update,
   onClearHistory,
   onOpenHistory,
+  onBookmarksChange,
   onResetOnboarding,
   isMobile,
   currentVersion,
@@ -572,15 +776,16 @@ function SectionContent({
   update: <K extends keyof BrowserSettings>(key: K, val: BrowserSettings[K]) => void
   onClearHistory: () => void
   onOpenHistory: () => void
+  onBookmarksChange: (tree: BookmarkTree) => void
   onResetOnboarding?: () => void
   isMobile?: boolean
   currentVersion: string
   updateAvailable: { version: string; releaseUrl: string } | null
   onUpdate: () => void
 }) {
-  if (section === "general")    return <GeneralSection settings={settings} update={update} isMobile={isMobile} />
+  if (section === "general")    return <GeneralSection settings={settings} update={update} onBookmarksChange={onBookmarksChange} isMobile={isMobile} />
   if (section === "appearance") return <AppearanceSection settings={settings} update={update} isMobile={isMobile} />
-  if (section === "privacy")    return <PrivacySection historyEnabled={settings.historyEnabled} onToggleHistory={(v) => update("historyEnabled", v)} onClearHistory={onClearHistory} onOpenHistory={onOpenHistory} isMobile={isMobile} />
+  if (section === "privacy")    return <PrivacySection historyEnabled={settings.historyEnabled} onToggleHistory={(v) => update("historyEnabled", v)} retentionDays={settings.historyRetentionDays} onRetentionChange={(days) => update("historyRetentionDays", days)} onClearHistory={onClearHistory} onOpenHistory={onOpenHistory} isMobile={isMobile} />
   if (section === "developers") return <DevelopersSection settings={settings} update={update} onResetOnboarding={onResetOnboarding} isMobile={isMobile} />
   if (section === "about")      return <AboutSection currentVersion={currentVersion} updateAvailable={updateAvailable} onUpdate={onUpdate} isMobile={isMobile} />
   return null
@@ -592,6 +797,7 @@ export function SettingsPage({
   onChange,
   onClearHistory,
   onOpenHistory,
+  onBookmarksChange,
   onResetOnboarding,
   isMobile = false,
   currentVersion = "",
@@ -602,6 +808,7 @@ export function SettingsPage({
   onChange: (s: BrowserSettings) => void
   onClearHistory: () => void
   onOpenHistory: () => void
+  onBookmarksChange: (tree: BookmarkTree) => void
   onResetOnboarding?: () => void
   isMobile?: boolean
   currentVersion?: string
@@ -665,6 +872,7 @@ export function SettingsPage({
                 update={update}
                 onClearHistory={onClearHistory}
                 onOpenHistory={onOpenHistory}
+                onBookmarksChange={onBookmarksChange}
                 onResetOnboarding={onResetOnboarding}
                 isMobile={true}
                 currentVersion={currentVersion}
@@ -712,6 +920,7 @@ export function SettingsPage({
             update={update}
             onClearHistory={onClearHistory}
             onOpenHistory={onOpenHistory}
+            onBookmarksChange={onBookmarksChange}
             onResetOnboarding={onResetOnboarding}
             currentVersion={currentVersion}
             updateAvailable={updateAvailable}
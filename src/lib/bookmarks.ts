@@ -1,75 +1,70 @@
+import { invoke } from "@tauri-apps/api/core"
+import { readTextFile, writeTextFile } from "@tauri-apps/plugin-fs"
+import { migrateLegacy } from "@/lib/legacy"
+
 export interface BookmarkNode {
   id: string
   type: "bookmark" | "folder"
   title: string
   url?: string
   children?: BookmarkNode[]
+  added?: number
 }
 
 export type BookmarkTree = BookmarkNode[]
 
-const STORAGE_KEY = "nwep-bookmarks-v2"
+export type NewBookmark = Pick<BookmarkNode, "type" | "title" | "url">
+
+export type BookmarkPatch = Partial<Pick<BookmarkNode, "title" | "url">>
+
+const LEGACY_STORAGE_KEY = "nwep-bookmarks-v2"
 
-let _seq = Date.now()
-export function genId(): string {
-  return `bk_${_seq++}`
+export async function loadBookmarks(): Promise<BookmarkTree> {
+  const migrated = await migrateLegacy<BookmarkTree>(LEGACY_STORAGE_KEY, "migrate_bookmarks", "tree")
+  return migrated ?? invoke<BookmarkTree>("list_bookmarks")
 }
 
-export function loadBookmarks(): BookmarkTree {
-  try {
-    return JSON.parse(localStorage.getItem(STORAGE_KEY) ?? "[]")
-  } catch {
-    return []
-  }
+export function addNode(parentId: string | null, node: NewBookmark): Promise<BookmarkTree> {
+  return invoke("add_bookmark", { parentId, node })
 }
 
-export function saveBookmarks(tree: BookmarkTree): void {
-  localStorage.setItem(STORAGE_KEY, JSON.stringify(tree))
+export function updateNode(id: string, patch: BookmarkPatch): Promise<BookmarkTree> {
+  return invoke("update_bookmark", { id, patch })
 }
 
-export function mapTree(
-  tree: BookmarkTree,
-  fn: (node: BookmarkNode) => BookmarkNode
-): BookmarkTree {
-  return tree.map((node) => {
-    const mapped = fn(node)
-    if (mapped.type === "folder" && mapped.children) {
-      return { ...mapped, children: mapTree(mapped.children, fn) }
-    }
-    return mapped
-  })
+export function removeNode(id: string): Promise<BookmarkTree> {
+  return invoke("remove_bookmark", { id })
 }
 
-export function addNode(
-  tree: BookmarkTree,
+export function removeByUrl(url: string): Promise<BookmarkTree> {
+  return invoke("remove_bookmarks_by_url", { url })
+}
+
+export function reorderInParent(
   parentId: string | null,
-  node: BookmarkNode
-): BookmarkTree {
-  if (parentId === null) return [...tree, node]
-  return mapTree(tree, (n) => {
-    if (n.id === parentId && n.type === "folder") {
-      return { ...n, children: [...(n.children ?? []), node] }
-    }
-    return n
-  })
+  fromIndex: number,
+  toIndex: number
+): Promise<BookmarkTree> {
+  return invoke("reorder_bookmarks", { parentId, fromIndex, toIndex })
 }
 
-export function updateNode(
-  tree: BookmarkTree,
-  id: string,
-  patch: Partial<BookmarkNode>
-): BookmarkTree {
-  return mapTree(tree, (n) => (n.id === id ? { ...n, ...patch } : n))
+export function moveNodeIntoFolder(
+  nodeId: string,
+  folderId: string | null
+): Promise<BookmarkTree> {
+  return invoke("move_bookmark", { id: nodeId, folderId })
 }
 
-export function removeNode(tree: BookmarkTree, id: string): BookmarkTree {
-  return tree
-    .filter((n) => n.id !== id)
-    .map((n) =>
-      n.type === "folder" && n.children
-        ? { ...n, children: removeNode(n.children, id) }
-        : n
-    )
+// `path` comes from a file dialog, which is what lets the fs plugin touch it;
+// the native store only ever sees the file's contents.
+export async function importBookmarks(path: string): Promise<BookmarkTree> {
+  const html = await readTextFile(path)
+  return invoke("import_bookmarks", { html })
+}
+
+export async function exportBookmarks(path: string): Promise<void> {
+  const html = await invoke<string>("export_bookmarks")
+  await writeTextFile(path, html)
 }
 
 export function flatBookmarks(
@@ -93,13 +88,6 @@ export function isBookmarked(tree: BookmarkTree, url: string): boolean {
   return flatBookmarks(tree).some((b) => b.url === url)
 }
 
-export function removeByUrl(tree: BookmarkTree, url: string): BookmarkTree {
-  const toRemove = flatBookmarks(tree).filter((b) => b.url === url)
-  let result = tree
-  for (const b of toRemove) result = removeNode(result, b.id)
-  return result
-}
-
 export function getNodeAtPath(
   tree: BookmarkTree,
   path: string[]
@@ -129,44 +117,3 @@ export function getFolderTitle(
   }
   return walk(tree)
 }
-
-export function reorderInParent(
-  tree: BookmarkTree,
-  parentId: string | null,
-  fromIndex: number,
-  toIndex: number
-): BookmarkTree {
-  const reorder = (nodes: BookmarkNode[]): BookmarkNode[] => {
-    const arr = [...nodes]
-    const [item] = arr.splice(fromIndex, 1)
-    arr.splice(toIndex, 0, item)
-    return arr
-  }
-  if (parentId === null) return reorder(tree)
-  return mapTree(tree, (node) =>
-    node.id === parentId && node.type === "folder" && node.children
-      ? { ...node, children: reorder(node.children) }
-      : node
-  )
-}
-
-export function moveNodeIntoFolder(
-  tree: BookmarkTree,
-  nodeId: string,
-  folderId: string | null
-): BookmarkTree {
-  let extracted: BookmarkNode | null = null
-  const without = (nodes: BookmarkTree): BookmarkTree =>
-    nodes.reduce<BookmarkTree>((acc, node) => {
-      if (node.id === nodeId) { extracted = node; return acc }
-      acc.push(
-        node.type === "folder" && node.children
-          ? { ...node, children: without(node.children) }
-          : node
-      )
-      return acc
-    }, [])
-  const stripped = without(tree)
-  if (!extracted) return tree
-  return addNode(stripped, folderId, extracted)
-}
@@ -0,0 +1,26 @@
+import { invoke } from "@tauri-apps/api/core"
+
+// Settings, bookmarks and history used to live in localStorage. The first load
+// hands the stored JSON to the native store's `command` and drops the old copy,
+// resolving to what the command returns. If the hand-off fails the copy stays
+// and is offered again next time; one that no longer parses is dropped.
+export async function migrateLegacy<T>(key: string, command: string, argument: string): Promise<T | undefined> {
+  const legacy = localStorage.getItem(key)
+  if (legacy === null) return undefined
+  let value: unknown
+  try {
+    value = JSON.parse(legacy)
+  } catch (e) {
+    console.error(e)
+    localStorage.removeItem(key)
+    return undefined
+  }
+  try {
+    const result = await invoke<T>(command, { [argument]: value })
+    localStorage.removeItem(key)
+    return result
+  } catch (e) {
+    console.error(e)
+    return undefined
+  }
+}
@@ -1,3 +1,6 @@
+import { Channel, invoke } from "@tauri-apps/api/core"
+import { migrateLegacy } from "@/lib/legacy"
+
 export interface BrowserSettings {
   homepage: string
   newTabAction: "empty" | "homepage"
@@ -6,6 +9,13 @@ export interface BrowserSettings {
   searchBookmarks: boolean
   searchHistory: boolean
   historyEnabled: boolean
+  historyRetentionDays: number
+  offlineMode: boolean
+  connectTimeoutMs: number
+  requestTimeoutMs: number
+  retryCount: number
+  retryBackoffMs: number
+  maxConcurrentRequests: number
   developerForceMobileUi: "auto" | "mobile" | "desktop"
 }
 
@@ -17,19 +27,35 @@ export const DEFAULT_SETTINGS: BrowserSettings = {
   searchBookmarks: true,
   searchHistory: true,
   historyEnabled: true,
+  historyRetentionDays: 365,
+  offlineMode: false,
+  connectTimeoutMs: 10000,
+  requestTimeoutMs: 30000,
+  retryCount: 2,
+  retryBackoffMs: 500,
+  maxConcurrentRequests: 6,
   developerForceMobileUi: "auto",
 }
 
-const KEY = "nwep-settings-v1"
+const LEGACY_STORAGE_KEY = "nwep-settings-v1"
 
-export function loadSettings(): BrowserSettings {
-  try {
-    return { ...DEFAULT_SETTINGS, ...JSON.parse(localStorage.getItem(KEY) ?? "{}") }
-  } catch {
-    return { ...DEFAULT_SETTINGS }
-  }
+export async function loadSettings(): Promise<BrowserSettings> {
+  const migrated = await migrateLegacy<BrowserSettings>(LEGACY_STORAGE_KEY, "migrate_settings", "legacy")
+  return migrated ?? invoke<BrowserSettings>("get_settings")
 }
 
-export function saveSettings(s: BrowserSettings): void {
-  localStorage.setItem(KEY, JSON.stringify(s))
+// Resolves to the settings as stored, after Rust has brought values into range.
+export function updateSettings(patch: Partial<BrowserSettings>): Promise<BrowserSettings> {
+  return invoke("set_settings", { patch })
+}
+
+// Calls `onChange` with the full settings after every change, whoever made
+// it. Returns the unsubscribe function.
+export function subscribeSettings(onChange: (settings: BrowserSettings) => void): () => void {
+  const channel = new Channel<BrowserSettings>()
+  channel.onmessage = onChange
+  const id = invoke<number>("subscribe_settings", { channel })
+  return () => {
+    id.then((id) => invoke("unsubscribe_settings", { id })).catch(console.error)
+  }
 }
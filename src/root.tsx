@@ -3,17 +3,27 @@ import ReactDOM from "react-dom/client";
 import { ThemeProvider } from "next-themes";
 
 import App from "./browser";
+import { DEFAULT_SETTINGS, loadSettings } from "./lib/settings";
 import "./browser.css";
 
-ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
-  <React.StrictMode>
-    <ThemeProvider
-      attribute="class"
-      defaultTheme="system"
-      enableSystem
-      disableTransitionOnChange
-    >
-      <App />
-    </ThemeProvider>
-  </React.StrictMode>,
-);
+// Settings are read before the first render so the UI never starts out with
+// the defaults and then jumps.
+loadSettings()
+  .catch((e) => {
+    console.error(e);
+    return DEFAULT_SETTINGS;
+  })
+  .then((settings) => {
+    ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
+      <React.StrictMode>
+        <ThemeProvider
+          attribute="class"
+          defaultTheme="system"
+          enableSystem
+          disableTransitionOnChange
+        >
+          <App initialSettings={settings} />
+        </ThemeProvider>
+      </React.StrictMode>,
+    );
+  });